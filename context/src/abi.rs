@@ -0,0 +1,108 @@
+//! Lowering of [`FuncTy`](crate::types::FuncTy) signatures to C calling conventions
+//!
+//! Each calling convention produces a [`FnAbi`], which describes where the return value
+//! and every argument live at the call boundary. Register indices are positions in the
+//! convention's argument (or return value) register sequence, not hardware encodings.
+
+pub mod aapcs64;
+pub mod sysv64;
+pub mod win64;
+
+#[cfg(test)]
+mod test_util;
+
+use crate::{
+    types::{ConcreteLayout, Layout, LayoutError, Type},
+    Context,
+};
+
+pub struct FnAbi<'ctx> {
+    pub ret: ArgAbi<'ctx>,
+    pub args: Vec<ArgAbi<'ctx>>,
+    /// The number of bytes of the stack argument area used by the call
+    pub stack_size: u64,
+}
+
+pub struct ArgAbi<'ctx> {
+    pub ty: Type<'ctx>,
+    pub mode: PassMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PassMode {
+    /// The value is zero-sized and isn't passed at all
+    Ignore,
+    /// The value is split into pieces, each of which is passed in a register
+    Direct(Vec<Piece>),
+    /// The value is copied into the stack argument area at the given offset
+    Stack { offset: u64 },
+    /// A pointer to a caller-owned copy of the value is passed at the given location
+    Indirect(Location),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece {
+    /// The byte offset of this piece within the value
+    pub offset: u64,
+    pub size: u64,
+    pub reg: Reg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    Int(u8),
+    Vector(u8),
+    X87(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Reg(Reg),
+    Stack { offset: u64 },
+}
+
+#[derive(Debug)]
+pub enum AbiError<'ctx> {
+    /// The type doesn't have a concrete layout, so it can't be passed by value
+    UnknownLayout(Type<'ctx>),
+    /// The type can't be passed under this calling convention
+    Unsupported(Type<'ctx>),
+    /// Computing the layout of a type failed
+    Layout(LayoutError),
+}
+
+impl<'ctx> ArgAbi<'ctx> {
+    const fn new(ty: Type<'ctx>, mode: PassMode) -> Self {
+        Self { ty, mode }
+    }
+}
+
+/// Look through any aliases to the type that decides how a value is passed, along with
+/// its layout
+///
+/// Every calling convention classifies types, including the fields of aggregates,
+/// through this, so none of them see an alias.
+fn resolve<'ctx>(
+    ctx: Context<'ctx>,
+    ty: Type<'ctx>,
+) -> Result<(Type<'ctx>, ConcreteLayout), AbiError<'ctx>> {
+    let ty = ty.strip_aliases();
+    Ok((ty, concrete_layout(ctx, ty)?))
+}
+
+fn concrete_layout<'ctx>(
+    ctx: Context<'ctx>,
+    ty: Type<'ctx>,
+) -> Result<ConcreteLayout, AbiError<'ctx>> {
+    match ty.layout(ctx).map_err(AbiError::Layout)? {
+        Layout::Concrete(layout) => Ok(layout),
+        Layout::RuntimeKnown | Layout::Unknown => Err(AbiError::UnknownLayout(ty)),
+    }
+}
+
+/// Reserve a slot in the stack argument area, every slot is aligned to at least `slot` bytes
+fn stack_slot(stack_size: &mut u64, layout: ConcreteLayout, slot: u64) -> u64 {
+    let offset = stack_size.next_multiple_of(layout.align.max(slot));
+    *stack_size = offset + layout.size.next_multiple_of(slot);
+    offset
+}
@@ -0,0 +1,442 @@
+//! The AArch64 Procedure Call Standard (AAPCS64), and its Apple arm64 variant
+//!
+//! `Reg::Int(n)` is `xn` and `Reg::Vector(n)` is `vn`. Arguments use `x0..=x7` and
+//! `v0..=v7`, and indirect results are returned through the memory pointed to by `x8`.
+
+use super::{AbiError, ArgAbi, FnAbi, Location, PassMode, Piece, Reg};
+use crate::{
+    types::{self, ConcreteLayout, FloatKind, Type, TypeKind},
+    Context,
+};
+
+const INT_ARG_REGS: u8 = 8;
+const FLOAT_ARG_REGS: u8 = 8;
+const INDIRECT_RESULT_REG: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    /// The standard AAPCS64, as used on Linux
+    Standard,
+    /// Apple's arm64 ABI, which packs stack arguments to their natural alignment
+    /// and passes all variadic arguments on the stack
+    Apple,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Zero-sized values aren't passed
+    Ignore,
+    /// Integers, pointers and composites of at most 16 bytes, passed in `x` registers
+    Integer,
+    /// Floats and homogeneous floating-point aggregates of `count` members of
+    /// `size` bytes each, passed in consecutive `v` registers
+    Float { count: u8, size: u64 },
+    /// Values larger than 16 bytes are passed by reference to a caller-owned copy
+    Indirect,
+}
+
+pub fn classify<'ctx>(ctx: Context<'ctx>, ty: Type<'ctx>) -> Result<Class, AbiError<'ctx>> {
+    let (ty, layout) = super::resolve(ctx, ty)?;
+
+    if layout.size == 0 {
+        return Ok(Class::Ignore);
+    }
+
+    if let Some((kind, count)) = homogeneous_aggregate(ctx, ty)? {
+        let size = super::concrete_layout(ctx, ctx.type_ctx().float(kind).erase())?.size;
+
+        if count <= 4 && u64::from(count) * size == layout.size {
+            return Ok(Class::Float { count, size });
+        }
+    }
+
+    if layout.size > 16 {
+        Ok(Class::Indirect)
+    } else {
+        Ok(Class::Integer)
+    }
+}
+
+/// If every scalar in `ty` is a float of the same kind, find that kind and how many
+/// of them there are, `ty` must not be an alias
+fn homogeneous_aggregate<'ctx>(
+    ctx: Context<'ctx>,
+    ty: Type<'ctx>,
+) -> Result<Option<(FloatKind, u8)>, AbiError<'ctx>> {
+    match ty.kind() {
+        TypeKind::Float => match ty.cast::<types::FloatTy>().float_kind() {
+            FloatKind::X87Extended80Bit => Err(AbiError::Unsupported(ty)),
+            kind => Ok(Some((kind, 1))),
+        },
+        TypeKind::Aggregate => {
+            let mut found = None::<(FloatKind, u8)>;
+
+            for field in ty.cast::<types::AggregateTy>().fields() {
+                let (field_ty, field_layout) = super::resolve(ctx, field.ty)?;
+                if field_layout.size == 0 {
+                    continue;
+                }
+
+                let Some((kind, count)) = homogeneous_aggregate(ctx, field_ty)? else {
+                    return Ok(None);
+                };
+
+                found = match found {
+                    None => Some((kind, count)),
+                    Some((found_kind, found_count)) if found_kind == kind => {
+                        Some((kind, found_count.saturating_add(count)))
+                    }
+                    Some(_) => return Ok(None),
+                };
+            }
+
+            Ok(found)
+        }
+        TypeKind::Alias => unreachable!("aliases are stripped by `resolve`"),
+        TypeKind::Unit
+        | TypeKind::Int
+        | TypeKind::Pointer
+        | TypeKind::FnPointer
+        | TypeKind::Func
+        | TypeKind::Param
+        | TypeKind::Opaque => Ok(None),
+    }
+}
+
+pub fn lower<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+    variant: Variant,
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    lower_variadic(ctx, func, &[], variant)
+}
+
+/// Lower a call to a variadic function, where `variadic` are the types of the
+/// arguments passed after the fixed arguments of `func`
+pub fn lower_variadic<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+    variadic: &[Type<'ctx>],
+    variant: Variant,
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    let ret = func.ret();
+    let ret = match classify(ctx, ret)? {
+        Class::Ignore => PassMode::Ignore,
+        Class::Integer => {
+            let size = super::concrete_layout(ctx, ret)?.size;
+            PassMode::Direct(int_pieces(size, 0))
+        }
+        Class::Float { count, size } => PassMode::Direct(float_pieces(count, size, 0)),
+        Class::Indirect => PassMode::Indirect(Location::Reg(Reg::Int(INDIRECT_RESULT_REG))),
+    };
+    let ret = ArgAbi::new(func.ret(), ret);
+
+    let mut state = State {
+        ngrn: 0,
+        nsrn: 0,
+        stack_size: 0,
+        variant,
+    };
+
+    let mut args = Vec::with_capacity(func.args().len() + variadic.len());
+
+    for &arg in func.args() {
+        args.push(ArgAbi::new(arg, state.arg(ctx, arg)?));
+    }
+
+    for &arg in variadic {
+        let mode = match variant {
+            Variant::Standard => state.arg(ctx, arg)?,
+            Variant::Apple => state.variadic_arg(ctx, arg)?,
+        };
+
+        args.push(ArgAbi::new(arg, mode));
+    }
+
+    Ok(FnAbi {
+        ret,
+        args,
+        stack_size: state.stack_size,
+    })
+}
+
+struct State {
+    /// the next general-purpose register number
+    ngrn: u8,
+    /// the next SIMD and floating-point register number
+    nsrn: u8,
+    stack_size: u64,
+    variant: Variant,
+}
+
+impl State {
+    fn arg<'ctx>(
+        &mut self,
+        ctx: Context<'ctx>,
+        ty: Type<'ctx>,
+    ) -> Result<PassMode, AbiError<'ctx>> {
+        let layout = super::concrete_layout(ctx, ty)?;
+
+        let mode = match classify(ctx, ty)? {
+            Class::Ignore => PassMode::Ignore,
+            Class::Float { count, size } => {
+                if self.nsrn + count <= FLOAT_ARG_REGS {
+                    let pieces = float_pieces(count, size, self.nsrn);
+                    self.nsrn += count;
+                    PassMode::Direct(pieces)
+                } else {
+                    self.nsrn = FLOAT_ARG_REGS;
+                    self.stack(layout)
+                }
+            }
+            Class::Integer => {
+                let count = layout.size.div_ceil(8) as u8;
+
+                if layout.align == 16 {
+                    self.ngrn = self.ngrn.next_multiple_of(2);
+                }
+
+                if self.ngrn + count <= INT_ARG_REGS {
+                    let pieces = int_pieces(layout.size, self.ngrn);
+                    self.ngrn += count;
+                    PassMode::Direct(pieces)
+                } else {
+                    self.ngrn = INT_ARG_REGS;
+                    self.stack(layout)
+                }
+            }
+            Class::Indirect => {
+                if self.ngrn < INT_ARG_REGS {
+                    self.ngrn += 1;
+                    PassMode::Indirect(Location::Reg(Reg::Int(self.ngrn - 1)))
+                } else {
+                    let pointer = super::concrete_layout(ctx, ctx.pointer_ty())?;
+                    PassMode::Indirect(Location::Stack {
+                        offset: self.stack_offset(pointer),
+                    })
+                }
+            }
+        };
+
+        Ok(mode)
+    }
+
+    /// Apple passes every variadic argument in its own 8-byte aligned stack slot
+    fn variadic_arg<'ctx>(
+        &mut self,
+        ctx: Context<'ctx>,
+        ty: Type<'ctx>,
+    ) -> Result<PassMode, AbiError<'ctx>> {
+        let mode = match classify(ctx, ty)? {
+            Class::Ignore => PassMode::Ignore,
+            Class::Integer | Class::Float { .. } => PassMode::Stack {
+                offset: super::stack_slot(
+                    &mut self.stack_size,
+                    super::concrete_layout(ctx, ty)?,
+                    8,
+                ),
+            },
+            Class::Indirect => PassMode::Indirect(Location::Stack {
+                offset: super::stack_slot(
+                    &mut self.stack_size,
+                    super::concrete_layout(ctx, ctx.pointer_ty())?,
+                    8,
+                ),
+            }),
+        };
+
+        Ok(mode)
+    }
+
+    fn stack(&mut self, layout: ConcreteLayout) -> PassMode {
+        PassMode::Stack {
+            offset: self.stack_offset(layout),
+        }
+    }
+
+    fn stack_offset(&mut self, layout: ConcreteLayout) -> u64 {
+        let slot = match self.variant {
+            Variant::Standard => 8,
+            Variant::Apple => 1,
+        };
+
+        super::stack_slot(&mut self.stack_size, layout, slot)
+    }
+}
+
+fn int_pieces(size: u64, first_reg: u8) -> Vec<Piece> {
+    (0..size.div_ceil(8))
+        .map(|i| Piece {
+            offset: 8 * i,
+            size: (size - 8 * i).min(8),
+            reg: Reg::Int(first_reg + i as u8),
+        })
+        .collect()
+}
+
+fn float_pieces(count: u8, size: u64, first_reg: u8) -> Vec<Piece> {
+    (0..count)
+        .map(|i| Piece {
+            offset: size * u64::from(i),
+            size,
+            reg: Reg::Vector(first_reg + i),
+        })
+        .collect()
+}
+
+#[test]
+fn test_known_signatures() {
+    use super::test_util::{aggregate, func, modes, piece};
+
+    const fn x(reg: u8, offset: u64, size: u64) -> Piece {
+        piece(Reg::Int(reg), offset, size)
+    }
+
+    const fn v(reg: u8, offset: u64, size: u64) -> Piece {
+        piece(Reg::Vector(reg), offset, size)
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let void = ctx.unit_ty();
+        let char = ctx.int_ty(8);
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+        let int128 = ctx.int_ty(128);
+        let float = ctx.float_32_ty();
+        let double = ctx.float_64_ty();
+        let ptr = ctx.pointer_ty();
+
+        let float3 = aggregate(ctx, "float3", &[float, float, float]);
+        let double2 = aggregate(ctx, "double2", &[double, double]);
+        let double4 = aggregate(ctx, "double4", &[double2, double2]);
+        let long2 = aggregate(ctx, "long2", &[long, long]);
+        let long3 = aggregate(ctx, "long3", &[long, long, long]);
+        let float_int = aggregate(ctx, "float_int", &[float, int]);
+
+        for variant in [Variant::Standard, Variant::Apple] {
+            // void f(int, double, char *)
+            let abi = lower(ctx, func(ctx, void, &[int, double, ptr]), variant).unwrap();
+            assert_eq!(abi.ret.mode, PassMode::Ignore);
+            assert_eq!(
+                modes(&abi),
+                [
+                    PassMode::Direct(vec![x(0, 0, 4)]),
+                    PassMode::Direct(vec![v(0, 0, 8)]),
+                    PassMode::Direct(vec![x(1, 0, 8)]),
+                ]
+            );
+
+            // struct { float, float, float } f(struct { float x 3 }, struct { double x 4 }, struct { float, int })
+            let abi = lower(
+                ctx,
+                func(ctx, float3, &[float3, double4, float_int]),
+                variant,
+            )
+            .unwrap();
+            assert_eq!(
+                abi.ret.mode,
+                PassMode::Direct(vec![v(0, 0, 4), v(1, 4, 4), v(2, 8, 4)])
+            );
+            assert_eq!(
+                modes(&abi),
+                [
+                    PassMode::Direct(vec![v(0, 0, 4), v(1, 4, 4), v(2, 8, 4)]),
+                    PassMode::Direct(vec![v(3, 0, 8), v(4, 8, 8), v(5, 16, 8), v(6, 24, 8)]),
+                    PassMode::Direct(vec![x(0, 0, 8)]),
+                ]
+            );
+
+            // struct { long x 3 } f(struct { long x 3 }, int, __int128, struct { long, long })
+            let abi = lower(ctx, func(ctx, long3, &[long3, int, int128, long2]), variant).unwrap();
+            assert_eq!(abi.ret.mode, PassMode::Indirect(Location::Reg(Reg::Int(8))));
+            assert_eq!(
+                modes(&abi),
+                [
+                    PassMode::Indirect(Location::Reg(Reg::Int(0))),
+                    PassMode::Direct(vec![x(1, 0, 4)]),
+                    PassMode::Direct(vec![x(2, 0, 8), x(3, 8, 8)]),
+                    PassMode::Direct(vec![x(4, 0, 8), x(5, 8, 8)]),
+                ]
+            );
+
+            // void f(double x 7, struct { double, double }, double)
+            let mut args = [double; 9];
+            args[7] = double2;
+            let abi = lower(ctx, func(ctx, void, &args), variant).unwrap();
+            assert_eq!(abi.args[6].mode, PassMode::Direct(vec![v(6, 0, 8)]));
+            assert_eq!(abi.args[7].mode, PassMode::Stack { offset: 0 });
+            assert_eq!(abi.args[8].mode, PassMode::Stack { offset: 16 });
+            assert_eq!(abi.stack_size, 24);
+        }
+
+        // typedef double real; void f(real, struct { real, double })
+        let real = ctx.create_alias("real", double);
+        let real_double = aggregate(ctx, "real_double", &[real, double]);
+        let abi = lower(
+            ctx,
+            func(ctx, void, &[real, real_double]),
+            Variant::Standard,
+        )
+        .unwrap();
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![v(0, 0, 8)]),
+                PassMode::Direct(vec![v(1, 0, 8), v(2, 8, 8)]),
+            ]
+        );
+
+        // void f(long x 8, char, char, int)
+        let sig = func(
+            ctx,
+            void,
+            &[
+                long, long, long, long, long, long, long, long, char, char, int,
+            ],
+        );
+        let abi = lower(ctx, sig, Variant::Standard).unwrap();
+        assert_eq!(
+            modes(&abi)[8..],
+            [
+                PassMode::Stack { offset: 0 },
+                PassMode::Stack { offset: 8 },
+                PassMode::Stack { offset: 16 },
+            ]
+        );
+        assert_eq!(abi.stack_size, 24);
+
+        let abi = lower(ctx, sig, Variant::Apple).unwrap();
+        assert_eq!(
+            modes(&abi)[8..],
+            [
+                PassMode::Stack { offset: 0 },
+                PassMode::Stack { offset: 1 },
+                PassMode::Stack { offset: 4 },
+            ]
+        );
+        assert_eq!(abi.stack_size, 8);
+
+        // int printf(const char *, ...) called with (int, double)
+        let printf = func(ctx, int, &[ptr]);
+        let abi = lower_variadic(ctx, printf, &[int, double], Variant::Standard).unwrap();
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![x(0, 0, 8)]),
+                PassMode::Direct(vec![x(1, 0, 4)]),
+                PassMode::Direct(vec![v(0, 0, 8)]),
+            ]
+        );
+
+        let abi = lower_variadic(ctx, printf, &[int, double], Variant::Apple).unwrap();
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![x(0, 0, 8)]),
+                PassMode::Stack { offset: 0 },
+                PassMode::Stack { offset: 8 },
+            ]
+        );
+        assert_eq!(abi.stack_size, 16);
+    })
+}
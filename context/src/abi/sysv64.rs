@@ -0,0 +1,404 @@
+//! The System V AMD64 psABI calling convention
+//!
+//! Integer registers are `rdi, rsi, rdx, rcx, r8, r9` for arguments and `rax, rdx` for
+//! return values. Vector registers are `xmm0..=xmm7` and `xmm0, xmm1` respectively.
+
+use super::{AbiError, ArgAbi, FnAbi, Location, PassMode, Piece, Reg};
+use crate::{
+    types::{self, FloatKind, Type, TypeKind},
+    Context,
+};
+
+const INT_ARG_REGS: u8 = 6;
+const SSE_ARG_REGS: u8 = 8;
+
+/// The class of a single eightbyte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    NoClass,
+    Integer,
+    Sse,
+    SseUp,
+    X87,
+    X87Up,
+    Memory,
+}
+
+impl Class {
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Class::NoClass, class) | (class, Class::NoClass) => class,
+            (Class::Memory, _) | (_, Class::Memory) => Class::Memory,
+            (Class::Integer, _) | (_, Class::Integer) => Class::Integer,
+            (Class::X87 | Class::X87Up, _) | (_, Class::X87 | Class::X87Up) => Class::Memory,
+            _ => Class::Sse,
+        }
+    }
+}
+
+/// Classify each eightbyte of `ty`, zero-sized types don't have any eightbytes
+pub fn classify<'ctx>(ctx: Context<'ctx>, ty: Type<'ctx>) -> Result<Vec<Class>, AbiError<'ctx>> {
+    let layout = super::concrete_layout(ctx, ty)?;
+    let mut classes = vec![Class::NoClass; layout.size.div_ceil(8) as usize];
+
+    if classes.is_empty() {
+        return Ok(classes);
+    }
+
+    if layout.size > 64 || !classify_into(ctx, ty, 0, &mut classes)? {
+        classes.fill(Class::Memory);
+        return Ok(classes);
+    }
+
+    let has_memory = classes.iter().enumerate().any(|(i, &class)| match class {
+        Class::Memory => true,
+        Class::X87Up => i == 0 || classes[i - 1] != Class::X87,
+        _ => false,
+    });
+    let is_vector =
+        classes[0] == Class::Sse && classes[1..].iter().all(|&class| class == Class::SseUp);
+
+    if has_memory || (classes.len() > 2 && !is_vector) {
+        classes.fill(Class::Memory);
+        return Ok(classes);
+    }
+
+    for i in 0..classes.len() {
+        if classes[i] == Class::SseUp
+            && (i == 0 || !matches!(classes[i - 1], Class::Sse | Class::SseUp))
+        {
+            classes[i] = Class::Sse;
+        }
+    }
+
+    Ok(classes)
+}
+
+/// Merge the classes of `ty` placed at `offset` into `classes`,
+/// returns false if `ty` contains unaligned fields
+fn classify_into<'ctx>(
+    ctx: Context<'ctx>,
+    ty: Type<'ctx>,
+    offset: u64,
+    classes: &mut [Class],
+) -> Result<bool, AbiError<'ctx>> {
+    let (ty, layout) = super::resolve(ctx, ty)?;
+
+    if offset % layout.align != 0 {
+        return Ok(false);
+    }
+
+    match ty.kind() {
+        TypeKind::Unit => (),
+        TypeKind::Int | TypeKind::Pointer | TypeKind::FnPointer => {
+            mark(classes, offset, layout.size, Class::Integer)
+        }
+        TypeKind::Float => match ty.cast::<types::FloatTy>().float_kind() {
+            FloatKind::Ieee16Bit | FloatKind::Ieee32Bit | FloatKind::Ieee64Bit => {
+                mark(classes, offset, layout.size, Class::Sse)
+            }
+            FloatKind::Ieee128Bit => {
+                mark(classes, offset, 8, Class::Sse);
+                mark(classes, offset + 8, 8, Class::SseUp);
+            }
+            FloatKind::X87Extended80Bit => {
+                mark(classes, offset, 8, Class::X87);
+                mark(classes, offset + 8, 8, Class::X87Up);
+            }
+        },
+        TypeKind::Aggregate => {
+            let aggregate = ty.cast::<types::AggregateTy>();
+            let layouts = aggregate
+                .field_layouts(ctx)
+                .map_err(AbiError::Layout)?
+                .ok_or(AbiError::UnknownLayout(ty))?;
+
+            for (field, field_layout) in aggregate.fields().iter().zip(layouts) {
+                // bitfields are classified by their storage unit, and zero-width
+                // bitfields don't have one
+                if field.bit_width == Some(0) {
+                    continue;
+                }
+
+                if !classify_into(ctx, field.ty, offset + field_layout.offset, classes)? {
+                    return Ok(false);
+                }
+            }
+        }
+        TypeKind::Alias => unreachable!("aliases are stripped by `resolve`"),
+        TypeKind::Func | TypeKind::Param | TypeKind::Opaque => {
+            return Err(AbiError::UnknownLayout(ty))
+        }
+    }
+
+    Ok(true)
+}
+
+fn mark(classes: &mut [Class], offset: u64, size: u64, class: Class) {
+    for i in offset / 8..(offset + size).div_ceil(8) {
+        classes[i as usize] = classes[i as usize].merge(class);
+    }
+}
+
+/// Assign registers to each eightbyte, merging `SSEUP` and `X87UP` eightbytes into the
+/// piece before them
+fn pieces(classes: &[Class], size: u64, int_regs: &mut u8, sse_regs: &mut u8) -> Vec<Piece> {
+    let mut pieces = Vec::<Piece>::with_capacity(classes.len());
+
+    for (i, &class) in classes.iter().enumerate() {
+        let offset = 8 * i as u64;
+        let piece_size = (size - offset).min(8);
+
+        let reg = match class {
+            Class::NoClass => continue,
+            Class::Integer => {
+                *int_regs += 1;
+                Reg::Int(*int_regs - 1)
+            }
+            Class::Sse => {
+                *sse_regs += 1;
+                Reg::Vector(*sse_regs - 1)
+            }
+            Class::X87 => Reg::X87(0),
+            Class::SseUp | Class::X87Up => {
+                let last = pieces
+                    .last_mut()
+                    .expect("upper halves always follow a lower half");
+                last.size += piece_size;
+                continue;
+            }
+            Class::Memory => unreachable!("memory class values aren't passed in registers"),
+        };
+
+        pieces.push(Piece {
+            offset,
+            size: piece_size,
+            reg,
+        })
+    }
+
+    pieces
+}
+
+fn count(classes: &[Class], class: Class) -> u8 {
+    classes.iter().filter(|&&c| c == class).count() as u8
+}
+
+pub fn lower<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    lower_variadic(ctx, func, &[])
+}
+
+/// Lower a call to a variadic function, where `variadic` are the types of the
+/// arguments passed after the fixed arguments of `func`
+///
+/// The caller must also set `al` to an upper bound on the number of vector
+/// registers used by the call.
+pub fn lower_variadic<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+    variadic: &[Type<'ctx>],
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    let mut int_regs = 0;
+    let mut sse_regs = 0;
+    let mut stack_size = 0;
+
+    let classes = classify(ctx, func.ret())?;
+    let ret = if classes.is_empty() {
+        PassMode::Ignore
+    } else if classes[0] == Class::Memory {
+        // the hidden return pointer is passed in the first integer register
+        int_regs += 1;
+        PassMode::Indirect(Location::Reg(Reg::Int(0)))
+    } else {
+        let size = super::concrete_layout(ctx, func.ret())?.size;
+        PassMode::Direct(pieces(&classes, size, &mut 0, &mut 0))
+    };
+    let ret = ArgAbi::new(func.ret(), ret);
+
+    let mut args = Vec::with_capacity(func.args().len() + variadic.len());
+
+    for &arg in func.args().iter().chain(variadic) {
+        let classes = classify(ctx, arg)?;
+        let layout = super::concrete_layout(ctx, arg)?;
+
+        let in_memory = classes
+            .iter()
+            .any(|class| matches!(class, Class::Memory | Class::X87 | Class::X87Up));
+        let needed_int = count(&classes, Class::Integer);
+        let needed_sse = count(&classes, Class::Sse);
+
+        let mode = if classes.is_empty() {
+            PassMode::Ignore
+        } else if in_memory
+            || int_regs + needed_int > INT_ARG_REGS
+            || sse_regs + needed_sse > SSE_ARG_REGS
+        {
+            PassMode::Stack {
+                offset: super::stack_slot(&mut stack_size, layout, 8),
+            }
+        } else {
+            PassMode::Direct(pieces(&classes, layout.size, &mut int_regs, &mut sse_regs))
+        };
+
+        args.push(ArgAbi::new(arg, mode));
+    }
+
+    Ok(FnAbi {
+        ret,
+        args,
+        stack_size,
+    })
+}
+
+#[test]
+fn test_known_signatures() {
+    use super::test_util::{aggregate, func, modes, piece};
+
+    fn lower_sig<'ctx>(ctx: Context<'ctx>, ret: Type<'ctx>, args: &[Type<'ctx>]) -> FnAbi<'ctx> {
+        lower(ctx, func(ctx, ret, args)).unwrap()
+    }
+
+    const fn gpr(reg: u8, offset: u64, size: u64) -> Piece {
+        piece(Reg::Int(reg), offset, size)
+    }
+
+    const fn xmm(reg: u8, offset: u64, size: u64) -> Piece {
+        piece(Reg::Vector(reg), offset, size)
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let void = ctx.unit_ty();
+        let char = ctx.int_ty(8);
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+        let int128 = ctx.int_ty(128);
+        let float = ctx.float_32_ty();
+        let double = ctx.float_64_ty();
+        let float128 = ctx.float_128_ty();
+        let long_double = ctx.float_x87_ty();
+        let ptr = ctx.pointer_ty();
+
+        let long2 = aggregate(ctx, "long2", &[long, long]);
+        let long3 = aggregate(ctx, "long3", &[long, long, long]);
+        let double2 = aggregate(ctx, "double2", &[double, double]);
+        let int_float = aggregate(ctx, "int_float", &[int, float]);
+        let float3 = aggregate(ctx, "float3", &[float, float, float]);
+        let long_then_double = aggregate(ctx, "long_then_double", &[long, double]);
+        let long_double_struct = aggregate(ctx, "long_double_struct", &[long_double]);
+        let char_double = aggregate(ctx, "char_double", &[char, double]);
+
+        // void f(int, double, char *)
+        let abi = lower_sig(ctx, void, &[int, double, ptr]);
+        assert_eq!(abi.ret.mode, PassMode::Ignore);
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![gpr(0, 0, 4)]),
+                PassMode::Direct(vec![xmm(0, 0, 8)]),
+                PassMode::Direct(vec![gpr(1, 0, 8)]),
+            ]
+        );
+
+        // struct { long, long } f(struct { double, double }, struct { int, float })
+        let abi = lower_sig(ctx, long2, &[double2, int_float]);
+        assert_eq!(
+            abi.ret.mode,
+            PassMode::Direct(vec![gpr(0, 0, 8), gpr(1, 8, 8)])
+        );
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![xmm(0, 0, 8), xmm(1, 8, 8)]),
+                PassMode::Direct(vec![gpr(0, 0, 8)]),
+            ]
+        );
+
+        // void f(struct { float, float, float }, struct { long, double }, struct { char, double })
+        let abi = lower_sig(ctx, void, &[float3, long_then_double, char_double]);
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![xmm(0, 0, 8), xmm(1, 8, 4)]),
+                PassMode::Direct(vec![gpr(0, 0, 8), xmm(2, 8, 8)]),
+                PassMode::Direct(vec![gpr(1, 0, 8), xmm(3, 8, 8)]),
+            ]
+        );
+
+        // struct { long, long, long } f(int, struct { long, long, long })
+        let abi = lower_sig(ctx, long3, &[int, long3]);
+        assert_eq!(abi.ret.mode, PassMode::Indirect(Location::Reg(Reg::Int(0))));
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![gpr(1, 0, 4)]),
+                PassMode::Stack { offset: 0 },
+            ]
+        );
+        assert_eq!(abi.stack_size, 24);
+
+        // long double f(long double, int)
+        let abi = lower_sig(ctx, long_double, &[long_double, int]);
+        assert_eq!(
+            abi.ret.mode,
+            PassMode::Direct(vec![piece(Reg::X87(0), 0, 16)])
+        );
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Stack { offset: 0 },
+                PassMode::Direct(vec![gpr(0, 0, 4)]),
+            ]
+        );
+        assert_eq!(abi.stack_size, 16);
+
+        // struct { long double } f(struct { long double }), classified as X87 and X87UP,
+        // so it is returned in st0 but passed in memory
+        let abi = lower_sig(ctx, long_double_struct, &[long_double_struct]);
+        assert_eq!(
+            abi.ret.mode,
+            PassMode::Direct(vec![piece(Reg::X87(0), 0, 16)])
+        );
+        assert_eq!(modes(&abi), [PassMode::Stack { offset: 0 }]);
+        assert_eq!(abi.stack_size, 16);
+
+        // __int128 f(__int128, __float128)
+        let abi = lower_sig(ctx, int128, &[int128, float128]);
+        assert_eq!(
+            abi.ret.mode,
+            PassMode::Direct(vec![gpr(0, 0, 8), gpr(1, 8, 8)])
+        );
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![gpr(0, 0, 8), gpr(1, 8, 8)]),
+                PassMode::Direct(vec![xmm(0, 0, 16)]),
+            ]
+        );
+
+        // void f(long, long, long, long, long, struct { long, long }, long)
+        let abi = lower_sig(ctx, void, &[long, long, long, long, long, long2, long]);
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![gpr(0, 0, 8)]),
+                PassMode::Direct(vec![gpr(1, 0, 8)]),
+                PassMode::Direct(vec![gpr(2, 0, 8)]),
+                PassMode::Direct(vec![gpr(3, 0, 8)]),
+                PassMode::Direct(vec![gpr(4, 0, 8)]),
+                PassMode::Stack { offset: 0 },
+                PassMode::Direct(vec![gpr(5, 0, 8)]),
+            ]
+        );
+        assert_eq!(abi.stack_size, 16);
+
+        // void f(double x 9)
+        let abi = lower_sig(ctx, void, &[double; 9]);
+        assert_eq!(abi.args[7].mode, PassMode::Direct(vec![xmm(7, 0, 8)]));
+        assert_eq!(abi.args[8].mode, PassMode::Stack { offset: 0 });
+        assert_eq!(abi.stack_size, 8);
+    })
+}
@@ -0,0 +1,30 @@
+//! Fixtures shared by the tests of each calling convention
+
+use super::{FnAbi, PassMode, Piece, Reg};
+use crate::{
+    types::{self, AggregateField, Type},
+    Context,
+};
+
+/// An aggregate with a field of each of `fields`
+pub fn aggregate<'ctx>(ctx: Context<'ctx>, name: &str, fields: &[Type<'ctx>]) -> Type<'ctx> {
+    ctx.create_aggregate(
+        name,
+        fields
+            .iter()
+            .map(|&ty| AggregateField::new(ctx.symbol("field"), ty)),
+    )
+}
+
+pub fn func<'ctx>(ctx: Context<'ctx>, ret: Type<'ctx>, args: &[Type<'ctx>]) -> types::FuncTy<'ctx> {
+    ctx.function(ret, args).cast::<types::FuncTy>()
+}
+
+/// How each argument is passed
+pub fn modes(abi: &FnAbi) -> Vec<PassMode> {
+    abi.args.iter().map(|arg| arg.mode.clone()).collect()
+}
+
+pub const fn piece(reg: Reg, offset: u64, size: u64) -> Piece {
+    Piece { offset, size, reg }
+}
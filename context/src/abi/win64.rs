@@ -0,0 +1,199 @@
+//! The Microsoft x64 calling convention
+//!
+//! Arguments are assigned by position: the first four use `rcx, rdx, r8, r9` or
+//! `xmm0..=xmm3`, so `Reg::Int(i)` and `Reg::Vector(i)` both mean the `i`th slot. Every
+//! argument owns an 8-byte slot in the stack argument area, and the first four slots
+//! form the home area that the caller always reserves, so stack offsets include it.
+
+use super::{AbiError, ArgAbi, FnAbi, Location, PassMode, Piece, Reg};
+use crate::{
+    types::{self, FloatKind, Type, TypeKind},
+    Context,
+};
+
+const ARG_REGS: u8 = 4;
+const SLOT_SIZE: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// Zero-sized values aren't passed
+    Ignore,
+    /// Integers, pointers and aggregates of exactly 1, 2, 4 or 8 bytes
+    Integer,
+    /// `f16`, `f32` and `f64`
+    Float,
+    /// Everything else is passed by reference to a caller-owned copy
+    Indirect,
+}
+
+pub fn classify<'ctx>(ctx: Context<'ctx>, ty: Type<'ctx>) -> Result<Class, AbiError<'ctx>> {
+    let (ty, layout) = super::resolve(ctx, ty)?;
+
+    if layout.size == 0 {
+        return Ok(Class::Ignore);
+    }
+
+    let class = match ty.kind() {
+        TypeKind::Float => match ty.cast::<types::FloatTy>().float_kind() {
+            FloatKind::Ieee16Bit | FloatKind::Ieee32Bit | FloatKind::Ieee64Bit => Class::Float,
+            FloatKind::Ieee128Bit | FloatKind::X87Extended80Bit => Class::Indirect,
+        },
+        _ if matches!(layout.size, 1 | 2 | 4 | 8) => Class::Integer,
+        _ => Class::Indirect,
+    };
+
+    Ok(class)
+}
+
+pub fn lower<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    lower_variadic(ctx, func, &[])
+}
+
+/// Lower a call to a variadic function, where `variadic` are the types of the
+/// arguments passed after the fixed arguments of `func`
+pub fn lower_variadic<'ctx>(
+    ctx: Context<'ctx>,
+    func: types::FuncTy<'ctx>,
+    variadic: &[Type<'ctx>],
+) -> Result<FnAbi<'ctx>, AbiError<'ctx>> {
+    let mut position = 0;
+
+    let ret = match classify(ctx, func.ret())? {
+        Class::Ignore => PassMode::Ignore,
+        Class::Integer => PassMode::Direct(vec![piece(ctx, func.ret(), Reg::Int(0))?]),
+        Class::Float => PassMode::Direct(vec![piece(ctx, func.ret(), Reg::Vector(0))?]),
+        Class::Indirect => {
+            // the hidden return pointer takes the first slot
+            position += 1;
+            PassMode::Indirect(Location::Reg(Reg::Int(0)))
+        }
+    };
+    let ret = ArgAbi::new(func.ret(), ret);
+
+    let fixed = func.args().iter().map(|&ty| (ty, false));
+    let variadic = variadic.iter().map(|&ty| (ty, true));
+    let mut args = Vec::with_capacity(fixed.len() + variadic.len());
+
+    for (arg, is_variadic) in fixed.chain(variadic) {
+        let class = classify(ctx, arg)?;
+        let in_reg = position < ARG_REGS;
+        let offset = SLOT_SIZE * u64::from(position);
+
+        let mode = match class {
+            Class::Ignore => PassMode::Ignore,
+            Class::Integer if in_reg => {
+                PassMode::Direct(vec![piece(ctx, arg, Reg::Int(position))?])
+            }
+            Class::Float if in_reg => {
+                let mut pieces = vec![piece(ctx, arg, Reg::Vector(position))?];
+                // variadic floats are also passed in the integer register, since the
+                // callee doesn't know their types
+                if is_variadic {
+                    pieces.push(piece(ctx, arg, Reg::Int(position))?);
+                }
+                PassMode::Direct(pieces)
+            }
+            Class::Indirect if in_reg => PassMode::Indirect(Location::Reg(Reg::Int(position))),
+            Class::Integer | Class::Float => PassMode::Stack { offset },
+            Class::Indirect => PassMode::Indirect(Location::Stack { offset }),
+        };
+
+        if class != Class::Ignore {
+            position += 1;
+        }
+
+        args.push(ArgAbi::new(arg, mode));
+    }
+
+    Ok(FnAbi {
+        ret,
+        args,
+        stack_size: SLOT_SIZE * u64::from(position.max(ARG_REGS)),
+    })
+}
+
+fn piece<'ctx>(ctx: Context<'ctx>, ty: Type<'ctx>, reg: Reg) -> Result<Piece, AbiError<'ctx>> {
+    Ok(Piece {
+        offset: 0,
+        size: super::concrete_layout(ctx, ty)?.size,
+        reg,
+    })
+}
+
+#[test]
+fn test_known_signatures() {
+    use super::test_util::{aggregate, func, modes, piece};
+
+    const fn reg(reg: Reg, size: u64) -> Piece {
+        piece(reg, 0, size)
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let void = ctx.unit_ty();
+        let char = ctx.int_ty(8);
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+        let float = ctx.float_32_ty();
+        let double = ctx.float_64_ty();
+        let ptr = ctx.pointer_ty();
+
+        let long2 = aggregate(ctx, "long2", &[long, long]);
+        let long3 = aggregate(ctx, "long3", &[long, long, long]);
+        let int2 = aggregate(ctx, "int2", &[int, int]);
+        let char3 = aggregate(ctx, "char3", &[char, char, char]);
+        let float2 = aggregate(ctx, "float2", &[float, float]);
+
+        // void f(int, double, struct { long, long }, float, long)
+        let abi = lower(ctx, func(ctx, void, &[int, double, long2, float, long])).unwrap();
+        assert_eq!(abi.ret.mode, PassMode::Ignore);
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![reg(Reg::Int(0), 4)]),
+                PassMode::Direct(vec![reg(Reg::Vector(1), 8)]),
+                PassMode::Indirect(Location::Reg(Reg::Int(2))),
+                PassMode::Direct(vec![reg(Reg::Vector(3), 4)]),
+                PassMode::Stack { offset: 32 },
+            ]
+        );
+        assert_eq!(abi.stack_size, 40);
+
+        // struct { int, int } f(struct { char, char, char }, struct { float, float })
+        let abi = lower(ctx, func(ctx, int2, &[char3, float2])).unwrap();
+        assert_eq!(abi.ret.mode, PassMode::Direct(vec![reg(Reg::Int(0), 8)]));
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Indirect(Location::Reg(Reg::Int(0))),
+                PassMode::Direct(vec![reg(Reg::Int(1), 8)]),
+            ]
+        );
+        assert_eq!(abi.stack_size, 32);
+
+        // struct { long, long, long } f(int, int, int, struct { long, long })
+        let abi = lower(ctx, func(ctx, long3, &[int, int, int, long2])).unwrap();
+        assert_eq!(abi.ret.mode, PassMode::Indirect(Location::Reg(Reg::Int(0))));
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![reg(Reg::Int(1), 4)]),
+                PassMode::Direct(vec![reg(Reg::Int(2), 4)]),
+                PassMode::Direct(vec![reg(Reg::Int(3), 4)]),
+                PassMode::Indirect(Location::Stack { offset: 32 }),
+            ]
+        );
+
+        // int printf(const char *, ...) called with (double)
+        let abi = lower_variadic(ctx, func(ctx, int, &[ptr]), &[double]).unwrap();
+        assert_eq!(
+            modes(&abi),
+            [
+                PassMode::Direct(vec![reg(Reg::Int(0), 8)]),
+                PassMode::Direct(vec![reg(Reg::Vector(1), 8), reg(Reg::Int(1), 8)]),
+            ]
+        );
+    })
+}
@@ -0,0 +1,153 @@
+use crate::types::FloatKind;
+
+/// The sizes of C's integer types and the representation of its target-dependent types
+///
+/// Start from one of the standard models and override the fields that differ on the
+/// target. AArch64 Linux, for example, is [`CDataModel::LP64`] with `char_signed: false`
+/// and `long_double: FloatKind::Ieee128Bit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CDataModel {
+    pub short_bits: u16,
+    pub int_bits: u16,
+    pub long_bits: u16,
+    pub long_long_bits: u16,
+    pub wchar_bits: u16,
+    pub wchar_signed: bool,
+    /// Whether plain `char` is signed
+    pub char_signed: bool,
+    pub long_double: FloatKind,
+}
+
+/// A C type whose representation depends on the target's [`CDataModel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CType {
+    Bool,
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    /// `size_t`, which has the same representation as `uintptr_t`
+    Size,
+    /// `ptrdiff_t`
+    PtrDiff,
+    IntPtr,
+    UIntPtr,
+    WChar,
+    Float,
+    Double,
+    LongDouble,
+}
+
+impl CDataModel {
+    /// 32-bit `int`, `long` and pointers, as on i386 Linux
+    ///
+    /// 32-bit Windows differs in having the `wchar_t` and `long double` of
+    /// [`CDataModel::LLP64`].
+    pub const ILP32: Self = Self {
+        short_bits: 16,
+        int_bits: 32,
+        long_bits: 32,
+        long_long_bits: 64,
+        wchar_bits: 32,
+        wchar_signed: true,
+        char_signed: true,
+        long_double: FloatKind::X87Extended80Bit,
+    };
+
+    /// 64-bit `long` and pointers, as on x86-64 Linux and macOS
+    pub const LP64: Self = Self {
+        short_bits: 16,
+        int_bits: 32,
+        long_bits: 64,
+        long_long_bits: 64,
+        wchar_bits: 32,
+        wchar_signed: true,
+        char_signed: true,
+        long_double: FloatKind::X87Extended80Bit,
+    };
+
+    /// 32-bit `long` and 64-bit pointers, as on 64-bit Windows
+    pub const LLP64: Self = Self {
+        short_bits: 16,
+        int_bits: 32,
+        long_bits: 32,
+        long_long_bits: 64,
+        wchar_bits: 16,
+        wchar_signed: false,
+        char_signed: true,
+        long_double: FloatKind::Ieee64Bit,
+    };
+}
+
+impl CType {
+    /// Whether values of this type are signed, or `None` for floating point types
+    pub const fn is_signed(self, model: &CDataModel) -> Option<bool> {
+        match self {
+            Self::Char => Some(model.char_signed),
+            Self::WChar => Some(model.wchar_signed),
+            Self::SignedChar
+            | Self::Short
+            | Self::Int
+            | Self::Long
+            | Self::LongLong
+            | Self::PtrDiff
+            | Self::IntPtr => Some(true),
+            Self::Bool
+            | Self::UnsignedChar
+            | Self::UnsignedShort
+            | Self::UnsignedInt
+            | Self::UnsignedLong
+            | Self::UnsignedLongLong
+            | Self::Size
+            | Self::UIntPtr => Some(false),
+            Self::Float | Self::Double | Self::LongDouble => None,
+        }
+    }
+}
+
+#[test]
+fn test_c_type() {
+    let windows = crate::TargetSpec {
+        c_data_model: CDataModel::LLP64,
+        ..crate::TEST_TARGET_SPEC
+    };
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        assert_eq!(ctx.c_type(CType::Long), ctx.int_ty(64));
+        assert_eq!(ctx.c_type(CType::Size), ctx.intptr_ty());
+        assert_eq!(ctx.c_type(CType::LongDouble), ctx.float_x87_ty());
+    });
+
+    let aarch64_linux = crate::TargetSpec {
+        c_data_model: CDataModel {
+            char_signed: false,
+            long_double: FloatKind::Ieee128Bit,
+            ..CDataModel::LP64
+        },
+        ..crate::TEST_TARGET_SPEC
+    };
+    crate::Context::with(aarch64_linux, |ctx| {
+        assert_eq!(ctx.c_type(CType::LongDouble), ctx.float_128_ty());
+        assert_eq!(
+            CType::Char.is_signed(&ctx.target().c_data_model),
+            Some(false)
+        );
+    });
+
+    crate::Context::with(windows, |ctx| {
+        assert_eq!(ctx.c_type(CType::Long), ctx.int_ty(32));
+        assert_eq!(ctx.c_type(CType::WChar), ctx.int_ty(16));
+        assert_eq!(ctx.c_type(CType::LongDouble), ctx.float_64_ty());
+        assert_eq!(
+            CType::WChar.is_signed(&ctx.target().c_data_model),
+            Some(false)
+        );
+    });
+}
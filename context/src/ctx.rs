@@ -1,23 +1,44 @@
-use std::{marker::PhantomData, num::NonZeroU16};
+use std::{marker::PhantomData, num::NonZeroU16, ptr::NonNull};
 
-use crate::{ptr::ContextPtr, TargetSpec};
+use crate::{
+    ptr::ContextPtr,
+    symbol::{Symbol, SymbolTable},
+    TargetError, TargetSpec,
+};
 
 #[derive(Clone, Copy)]
 pub struct ContextId<'ctx>(PhantomData<fn() -> *mut &'ctx mut ()>);
 
+mod arena;
+mod checkpoint;
+mod stats;
 mod ty;
 
+pub use checkpoint::Checkpoint;
+pub use stats::{CacheStats, ContextStats, LARGEST_TYPES};
+pub use ty::Namespace;
+
 struct ContextData<'ctx> {
     id: ContextId<'ctx>,
     target: TargetSpec,
     ty: ty::TypeContextData<'ctx>,
-    // put Bump last so if anything tries to access it on drop, the data will not be
+    symbols: SymbolTable<'ctx>,
+    checkpoints: checkpoint::Checkpoints,
+    // put Arena last so if anything tries to access it on drop, the data will not be
     // used after bump is dropped. Thus hardenning against use after frees.
     // We currently cannot guarantee this on stable, because #[may_dangle] is not stable
     // so hashbrown doesn't implement it on stable.
-    bump: bumpme::Bump,
+    arena: arena::Arena,
 }
 
+/// A handle to a context, branded with `'ctx` so its types can't be mixed with the
+/// types of another context
+///
+/// A `Context` can't create checkpoints or roll back. Every type it hands out is `Copy`
+/// and lives for all of `'ctx`, so nothing could stop a discarded type from being used
+/// after a rollback. To discard types, create the context with [`OwnedContext::new`]
+/// and use [`OwnedContext::checkpoint`] and [`OwnedContext::rollback`] between calls
+/// to [`OwnedContext::with`].
 #[derive(Clone, Copy)]
 pub struct Context<'ctx>(ContextPtr<'ctx, ContextData<'ctx>>);
 
@@ -25,16 +46,40 @@ pub struct Context<'ctx>(ContextPtr<'ctx, ContextData<'ctx>>);
 pub struct TypeContext<'ctx>(ContextPtr<'ctx, ty::TypeContextData<'ctx>>);
 
 #[derive(Clone, Copy)]
-pub struct AllocContext<'ctx>(ContextPtr<'ctx, bumpme::Bump>);
+pub struct AllocContext<'ctx>(ContextPtr<'ctx, arena::Arena>);
+
+/// A heap-allocated context, which can be stored in long-lived data structures
+///
+/// The context can only be accessed through [`OwnedContext::with`], which hands out a
+/// freshly branded [`Context`] each time, so types from one call can't escape or be
+/// mixed with types from another context.
+pub struct OwnedContext {
+    data: NonNull<ContextData<'static>>,
+}
 
 impl<'ctx> Context<'ctx> {
+    /// Create a context for `target` and pass it to `f`
+    ///
+    /// # Panics
+    ///
+    /// If `target` fails [`TargetSpec::validate`]
     pub fn with<T>(target: TargetSpec, f: impl FnOnce(Context<'_>) -> T) -> T {
+        Self::try_with(target, f).unwrap_or_else(|err| panic!("invalid target: {err}"))
+    }
+
+    /// Create a context for `target` and pass it to `f`, or return why `target` was
+    /// rejected without calling `f`
+    pub fn try_with<T>(
+        target: TargetSpec,
+        f: impl FnOnce(Context<'_>) -> T,
+    ) -> Result<T, TargetError> {
+        target.validate()?;
         let ctx_data: ContextData =
             init::try_init_on_stack(target).unwrap_or_else(|inf| match inf {});
         let ctx = Context(unsafe {
             ContextPtr::new_unchecked(ctx_data.id, core::ptr::addr_of!(ctx_data))
         });
-        f(ctx)
+        Ok(f(ctx))
     }
 
     #[inline]
@@ -57,7 +102,19 @@ impl<'ctx> Context<'ctx> {
     #[inline]
     pub const fn alloc_ctx(self) -> AllocContext<'ctx> {
         let ptr = self.0.as_ref();
-        AllocContext(ContextPtr::from_ref(self.id(), &ptr.bump))
+        AllocContext(ContextPtr::from_ref(self.id(), &ptr.arena))
+    }
+
+    /// Intern `name` in this context
+    #[inline]
+    pub fn symbol(self, name: &str) -> Symbol<'ctx> {
+        self.0.as_ref().symbols.intern(self.alloc_ctx(), name)
+    }
+
+    /// The symbol for `name`, if it was already interned
+    #[inline]
+    pub fn get_symbol(self, name: &str) -> Option<Symbol<'ctx>> {
+        self.0.as_ref().symbols.get(name)
     }
 
     #[inline]
@@ -70,6 +127,13 @@ impl<'ctx> Context<'ctx> {
         self.type_ctx().pointer().erase()
     }
 
+    #[inline]
+    pub fn pointer_ty_in(self, address_space: u32) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .pointer_in(self.alloc_ctx(), address_space)
+            .erase()
+    }
+
     #[inline]
     pub fn int_ty(self, bits: u16) -> crate::types::Type<'ctx> {
         self.type_ctx()
@@ -108,31 +172,154 @@ impl<'ctx> Context<'ctx> {
             .erase()
     }
 
+    #[inline]
+    pub const fn float_x87_ty(self) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .float(crate::types::FloatKind::X87Extended80Bit)
+            .erase()
+    }
+
+    #[inline]
+    pub const fn intptr_ty(self) -> crate::types::Type<'ctx> {
+        self.type_ctx().intptr().erase()
+    }
+
+    #[inline]
+    pub const fn intptr_diff_ty(self) -> crate::types::Type<'ctx> {
+        self.type_ctx().intptr_diff().erase()
+    }
+
+    /// The representation of `ty` under the target's C data model
+    #[inline]
+    pub fn c_type(self, ty: crate::CType) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .c_type(self.alloc_ctx(), &self.target().c_data_model, ty)
+    }
+
+    /// The aggregate, opaque type or alias with the full path `name`
+    #[inline]
+    pub fn get_named(self, name: &str) -> Option<crate::types::Type<'ctx>> {
+        self.type_ctx().get_named(self.get_symbol(name)?)
+    }
+
+    /// The namespace at `path`, if any named type is declared in or below it
+    #[inline]
+    pub fn namespace(self, path: &str) -> Option<Namespace<'ctx>> {
+        self.type_ctx().get_namespace(path)
+    }
+
+    /// Look up the named type at `path` relative to `scope`, trying each enclosing
+    /// namespace in turn out to the root
+    pub fn resolve(self, scope: Namespace<'ctx>, path: &str) -> Option<crate::types::Type<'ctx>> {
+        let mut namespace = Some(scope);
+        while let Some(current) = namespace {
+            if let Some(ty) = self.get_named(&current.join(path)) {
+                return Some(ty);
+            }
+            namespace = current.parent();
+        }
+
+        None
+    }
+
+    #[inline]
+    pub fn create_opaque(self, name: &str) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .create_opaque(self.alloc_ctx(), self.symbol(name))
+            .erase()
+    }
+
+    #[inline]
+    pub fn create_alias(
+        self,
+        name: &str,
+        aliased: crate::types::Type<'ctx>,
+    ) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .create_alias(self.alloc_ctx(), self.symbol(name), aliased)
+            .erase()
+    }
+
     #[inline]
     pub fn get_aggregate(self, name: &str) -> Option<crate::types::Type<'ctx>> {
         self.type_ctx()
-            .get_aggregate(istr::IBytes::new(name.as_bytes()))
+            .get_aggregate(self.get_symbol(name)?)
             .map(crate::types::AggregateTy::erase)
     }
 
     #[inline]
     pub fn aggregate(self, name: &str) -> crate::types::Type<'ctx> {
+        let name = self
+            .get_symbol(name)
+            .unwrap_or_else(|| panic!("no aggregate named {name:?}"));
+        self.type_ctx().aggregate(name).erase()
+    }
+
+    #[inline]
+    pub fn create_aggregate<I>(self, name: &str, fields: I) -> crate::types::Type<'ctx>
+    where
+        I: IntoIterator<Item = crate::types::AggregateField<'ctx>>,
+        I::IntoIter: ExactSizeIterator,
+    {
         self.type_ctx()
-            .aggregate(istr::IBytes::new(name.as_bytes()))
+            .create_aggregate(self.alloc_ctx(), self.symbol(name), fields)
             .erase()
     }
 
     #[inline]
-    pub fn create_aggregate<I>(self, name: &str, fields: I) -> crate::types::Type<'ctx>
+    pub fn create_aggregate_with<I>(
+        self,
+        name: &str,
+        repr: crate::types::AggregateRepr,
+        fields: I,
+    ) -> crate::types::Type<'ctx>
+    where
+        I: IntoIterator<Item = crate::types::AggregateField<'ctx>>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.type_ctx()
+            .create_aggregate_with(self.alloc_ctx(), self.symbol(name), repr, fields)
+            .erase()
+    }
+
+    #[inline]
+    pub fn create_generic_aggregate<I>(
+        self,
+        name: &str,
+        params: u32,
+        fields: I,
+    ) -> crate::types::Type<'ctx>
     where
         I: IntoIterator<Item = crate::types::AggregateField<'ctx>>,
         I::IntoIter: ExactSizeIterator,
     {
         self.type_ctx()
-            .create_aggregate(self.alloc_ctx(), istr::IBytes::new(name.as_bytes()), fields)
+            .create_generic_aggregate(
+                self.alloc_ctx(),
+                self.symbol(name),
+                params,
+                crate::types::AggregateRepr::default(),
+                fields,
+            )
             .erase()
     }
 
+    #[inline]
+    pub fn param_ty(self, index: u32) -> crate::types::Type<'ctx> {
+        self.type_ctx().param(self.alloc_ctx(), index).erase()
+    }
+
+    /// Substitute `args` for the type parameters in `generic`, see
+    /// [`TypeContext::instantiate`]
+    #[inline]
+    pub fn instantiate(
+        self,
+        generic: crate::types::Type<'ctx>,
+        args: &[crate::types::Type<'ctx>],
+    ) -> crate::types::Type<'ctx> {
+        self.type_ctx().instantiate(self.alloc_ctx(), generic, args)
+    }
+
     #[inline]
     pub fn function(
         self,
@@ -143,6 +330,87 @@ impl<'ctx> Context<'ctx> {
             .function(self.alloc_ctx(), ret, args)
             .erase()
     }
+
+    #[inline]
+    pub fn attributes(
+        self,
+        ty: crate::types::Type<'ctx>,
+    ) -> Option<crate::types::Attributes<'ctx>> {
+        self.type_ctx().attributes(ty)
+    }
+
+    #[inline]
+    pub fn set_attributes(
+        self,
+        ty: crate::types::Type<'ctx>,
+        attrs: crate::types::Attributes<'ctx>,
+    ) {
+        self.type_ctx().set_attributes(ty, attrs)
+    }
+
+    #[inline]
+    pub fn update_attributes(
+        self,
+        ty: crate::types::Type<'ctx>,
+        f: impl FnOnce(&mut crate::types::Attributes<'ctx>),
+    ) {
+        self.type_ctx().update_attributes(ty, f)
+    }
+
+    #[inline]
+    pub fn fn_ptr_ty(self, func: crate::types::Type<'ctx>) -> crate::types::Type<'ctx> {
+        self.type_ctx()
+            .fn_pointer(self.alloc_ctx(), func.cast::<crate::types::FuncTy>())
+            .erase()
+    }
+}
+
+impl OwnedContext {
+    /// # Panics
+    ///
+    /// If `target` fails [`TargetSpec::validate`]
+    pub fn new(target: TargetSpec) -> Self {
+        Self::try_new(target).unwrap_or_else(|err| panic!("invalid target: {err}"))
+    }
+
+    /// Create a context for `target`, or return why `target` was rejected
+    pub fn try_new(target: TargetSpec) -> Result<Self, TargetError> {
+        target.validate()?;
+        let layout = std::alloc::Layout::new::<ContextData>();
+        let ptr = unsafe { std::alloc::alloc(layout) }.cast::<ContextData>();
+        let Some(data) = NonNull::new(ptr) else {
+            std::alloc::handle_alloc_error(layout)
+        };
+
+        let ptr = unsafe { init::ptr::Uninit::from_raw(data.as_ptr()) };
+        ptr.try_init(target)
+            .unwrap_or_else(|inf| match inf {})
+            .into_raw();
+
+        Ok(Self { data })
+    }
+
+    pub fn with<T>(&self, f: impl FnOnce(Context<'_>) -> T) -> T {
+        let data = self.data.as_ptr().cast::<ContextData>().cast_const();
+        let ctx = Context(unsafe { ContextPtr::new_unchecked(ContextId(PhantomData), data) });
+        f(ctx)
+    }
+
+    pub fn target(&self) -> &TargetSpec {
+        unsafe { &self.data.as_ref().target }
+    }
+}
+
+impl Drop for OwnedContext {
+    fn drop(&mut self) {
+        unsafe {
+            core::ptr::drop_in_place(self.data.as_ptr());
+            std::alloc::dealloc(
+                self.data.as_ptr().cast(),
+                std::alloc::Layout::new::<ContextData>(),
+            );
+        }
+    }
 }
 
 impl<'ctx> AllocContext<'ctx> {
@@ -151,20 +419,110 @@ impl<'ctx> AllocContext<'ctx> {
         ContextId(PhantomData)
     }
 
-    pub(crate) fn try_init<T, Args, L>(self, args: Args) -> Result<ContextPtr<'ctx, T>, Args::Error>
+    pub(crate) fn init_ptr<T, Args, L>(self, args: Args) -> Result<ContextPtr<'ctx, T>, Args::Error>
     where
         T: ?Sized,
         Args: init::Initializer<T>,
         L: init::layout_provider::LayoutProvider<T, Args>,
     {
         let layout = L::layout_for(&args).expect("could not construct layout");
-        let bump = unsafe { &*self.0.as_ptr() };
-        let ptr = bump.alloc_layout(layout).into_raw();
+        let ptr = self.0.as_ref().alloc_layout(layout);
         let ptr = unsafe { L::cast(ptr, &args) };
         let ptr = unsafe { init::ptr::Uninit::from_raw(ptr.as_ptr()) };
         let ptr = ptr.try_init(args)?.into_raw();
         Ok(unsafe { ContextPtr::new_unchecked(self.id(), ptr) })
     }
+
+    /// Move `value` into the context
+    ///
+    /// The value's destructor is never run, use [`AllocContext::alloc_with_drop`] for
+    /// values that own resources.
+    pub fn alloc<T>(self, value: T) -> &'ctx mut T {
+        self.try_init(init::init(value))
+            .unwrap_or_else(|inf| match inf {})
+    }
+
+    pub fn alloc_slice_copy<T: Copy>(self, values: &[T]) -> &'ctx mut [T] {
+        let layout = std::alloc::Layout::for_value(values);
+        let ptr = self.0.as_ref().alloc_layout(layout).cast::<T>().as_ptr();
+        unsafe {
+            ptr.copy_from_nonoverlapping(values.as_ptr(), values.len());
+            core::slice::from_raw_parts_mut(ptr, values.len())
+        }
+    }
+
+    pub fn alloc_str(self, value: &str) -> &'ctx mut str {
+        let bytes = self.alloc_slice_copy(value.as_bytes());
+        unsafe { core::str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// Collect `iter` into a slice in the context, like [`AllocContext::alloc`] the
+    /// elements are never dropped
+    pub fn alloc_from_iter<T, I>(self, iter: I) -> &'ctx mut [T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut values = iter.into_iter().collect::<Vec<T>>();
+        let layout = std::alloc::Layout::array::<T>(values.len()).expect("slice is too large");
+        let ptr = self.0.as_ref().alloc_layout(layout).cast::<T>().as_ptr();
+        unsafe {
+            ptr.copy_from_nonoverlapping(values.as_ptr(), values.len());
+            // the values were moved into the context
+            let len = values.len();
+            values.set_len(0);
+            core::slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+
+    /// Construct a value in place in the context
+    pub fn try_init<T, Args>(self, args: Args) -> Result<&'ctx mut T, Args::Error>
+    where
+        Args: init::Initializer<T>,
+    {
+        self.try_init_with::<T, Args, init::layout_provider::SizedLayout>(args)
+    }
+
+    /// Construct a possibly unsized value in place in the context, using `L` to
+    /// compute its layout
+    pub fn try_init_with<T, Args, L>(self, args: Args) -> Result<&'ctx mut T, Args::Error>
+    where
+        T: ?Sized,
+        Args: init::Initializer<T>,
+        L: init::layout_provider::LayoutProvider<T, Args>,
+    {
+        let ptr = self.init_ptr::<T, Args, L>(args)?;
+        Ok(unsafe { &mut *ptr.as_ptr().cast_mut() })
+    }
+
+    /// Move `value` into the context, and drop it when the context ends
+    ///
+    /// This requires `T: 'static` so the destructor can't observe other values in the
+    /// context, which may have already been dropped.
+    pub fn alloc_with_drop<T: 'static>(self, value: T) -> &'ctx mut T {
+        unsafe { self.alloc_with_drop_unchecked(value) }
+    }
+
+    /// Move `value` into the context, and drop it when the context ends
+    ///
+    /// Values are dropped in the reverse order they were allocated, before any of the
+    /// context's memory is released.
+    ///
+    /// # Safety
+    ///
+    /// The destructor of `T` must not access any value in the context which was
+    /// allocated with a destructor after this one.
+    pub unsafe fn alloc_with_drop_unchecked<T>(self, value: T) -> &'ctx mut T {
+        let value = self.alloc(value);
+        unsafe { self.0.as_ref().register_drop(NonNull::from(&mut *value)) };
+        value
+    }
+}
+
+impl Drop for ContextData<'_> {
+    fn drop(&mut self) {
+        // run destructors of values in the arena while the type context is still alive
+        self.arena.run_drops();
+    }
 }
 
 impl<'ctx> init::Ctor<TargetSpec> for ContextData<'ctx> {
@@ -178,12 +536,79 @@ impl<'ctx> init::Ctor<TargetSpec> for ContextData<'ctx> {
             ptr => Self {
                 id: init::init(ContextId(PhantomData)),
                 target: init::init(spec),
-                bump: init::init(bumpme::Bump::new()),
+                arena: init::init(arena::Arena::new()),
+                symbols: init::init(SymbolTable::default()),
+                checkpoints: init::init(checkpoint::Checkpoints::default()),
                 ty: ty::TypeContextDataArgs {
-                    alloc:  AllocContext(unsafe { ContextPtr::new_unchecked(*id, bump.as_ptr()) }),
+                    alloc:  AllocContext(unsafe { ContextPtr::new_unchecked(*id, arena.as_ptr()) }),
                     target: &target,
                 }
             }
         }
     }
 }
+
+#[test]
+fn test_owned_context() {
+    struct Compiler {
+        ctx: OwnedContext,
+    }
+
+    let compiler = Compiler {
+        ctx: OwnedContext::new(crate::TEST_TARGET_SPEC),
+    };
+
+    compiler.ctx.with(|ctx| {
+        ctx.create_aggregate(
+            "point",
+            [crate::types::AggregateField::new(
+                ctx.symbol("x"),
+                ctx.int_ty(32),
+            )],
+        );
+    });
+
+    let size = compiler.ctx.with(|ctx| {
+        let point = ctx.get_aggregate("point").unwrap();
+        point.layout(ctx).unwrap()
+    });
+    assert!(matches!(size, crate::types::Layout::Concrete(layout) if layout.size() == 4));
+
+    let invalid = TargetSpec {
+        pointer_size_bytes: 6,
+        ..crate::TEST_TARGET_SPEC
+    };
+    let err = TargetError::UnsupportedSize {
+        field: "pointer",
+        bytes: 6,
+    };
+    assert_eq!(OwnedContext::try_new(invalid).err(), Some(err));
+    assert_eq!(Context::try_with(invalid, |_| unreachable!()), Err(err));
+}
+
+#[test]
+fn test_arena() {
+    let dropped = std::rc::Rc::new(core::cell::Cell::new(0));
+
+    struct Guard(std::rc::Rc<core::cell::Cell<u32>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let alloc = ctx.alloc_ctx();
+        let name = alloc.alloc_str("hello");
+        let squares = alloc.alloc_from_iter((0..4u32).map(|x| x * x));
+        assert_eq!(name, "hello");
+        assert_eq!(squares, [0, 1, 4, 9]);
+
+        alloc.alloc_with_drop(Guard(dropped.clone()));
+        alloc.alloc_with_drop(Guard(dropped.clone()));
+        assert_eq!(dropped.get(), 0);
+    });
+
+    assert_eq!(dropped.get(), 2);
+}
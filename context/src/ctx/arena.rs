@@ -0,0 +1,180 @@
+use std::{
+    cell::{Cell, UnsafeCell},
+    ptr::NonNull,
+};
+
+/// The size of the chunks the arena reserves from its bump allocators
+const CHUNK_SIZE: usize = 64 * 1024;
+const CHUNK_ALIGN: usize = 16;
+
+/// The bump allocator backing a context, along with the destructors that must run
+/// before its memory is released
+pub(super) struct Arena {
+    drops: UnsafeCell<Vec<DropEntry>>,
+    used: Cell<usize>,
+    reserved: Cell<usize>,
+    /// A scope for each checkpoint, new values are allocated in the last one so they
+    /// can be freed on rollback without touching older allocations
+    scopes: UnsafeCell<Vec<Scope>>,
+    root: Scope,
+}
+
+/// A bump allocator that values are carved out of in chunks, so the arena knows how
+/// much memory it has reserved
+struct Scope {
+    bump: bumpme::Bump,
+    /// The unused end of the current chunk, and its length
+    free: Cell<(NonNull<u8>, usize)>,
+}
+
+/// The state of an [`Arena`] when a checkpoint was created
+#[derive(Clone, Copy)]
+pub(super) struct ArenaMark {
+    drops: usize,
+    used: usize,
+    reserved: usize,
+    scopes: usize,
+}
+
+struct DropEntry {
+    ptr: NonNull<u8>,
+    drop: unsafe fn(NonNull<u8>),
+}
+
+unsafe fn drop_erased<T>(ptr: NonNull<u8>) {
+    unsafe { ptr.cast::<T>().drop_in_place() }
+}
+
+impl Scope {
+    fn new() -> Self {
+        Self {
+            bump: bumpme::Bump::new(),
+            free: Cell::new((NonNull::dangling(), 0)),
+        }
+    }
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self {
+            drops: UnsafeCell::new(Vec::new()),
+            used: Cell::new(0),
+            reserved: Cell::new(0),
+            scopes: UnsafeCell::new(Vec::new()),
+            root: Scope::new(),
+        }
+    }
+
+    pub fn alloc_layout(&self, layout: std::alloc::Layout) -> NonNull<u8> {
+        self.used.set(self.used.get() + layout.size());
+        let scopes = unsafe { &*self.scopes.get() };
+        let scope = scopes.last().unwrap_or(&self.root);
+
+        let (free, len) = scope.free.get();
+        let padding = free.as_ptr().align_offset(layout.align());
+        if padding <= len && layout.size() <= len - padding {
+            let ptr = unsafe { free.add(padding) };
+            let rest = padding + layout.size();
+            scope.free.set((unsafe { free.add(rest) }, len - rest));
+            return ptr;
+        }
+
+        // large values get a chunk of their own, so the rest of the current chunk
+        // isn't wasted
+        if layout.size() + layout.align() > CHUNK_SIZE / 4 {
+            self.reserved.set(self.reserved.get() + layout.size());
+            return scope.bump.alloc_layout(layout).into_raw();
+        }
+
+        let chunk = std::alloc::Layout::from_size_align(CHUNK_SIZE, CHUNK_ALIGN).unwrap();
+        let chunk = scope.bump.alloc_layout(chunk).into_raw();
+        self.reserved.set(self.reserved.get() + CHUNK_SIZE);
+
+        // values are at most a quarter of the chunk, so this can't overflow it
+        let padding = chunk.as_ptr().align_offset(layout.align());
+        let rest = padding + layout.size();
+        scope
+            .free
+            .set((unsafe { chunk.add(rest) }, CHUNK_SIZE - rest));
+        unsafe { chunk.add(padding) }
+    }
+
+    /// Start a new scope, everything allocated after this can be freed with
+    /// [`Arena::reset`]
+    pub fn mark(&self) -> ArenaMark {
+        let scopes = unsafe { &mut *self.scopes.get() };
+        let mark = ArenaMark {
+            drops: unsafe { (*self.drops.get()).len() },
+            used: self.used.get(),
+            reserved: self.reserved.get(),
+            scopes: scopes.len(),
+        };
+        scopes.push(Scope::new());
+        mark
+    }
+
+    /// Drop every value registered since `mark` was created, without freeing memory
+    ///
+    /// # Safety
+    ///
+    /// None of the dropped values may be used after this call
+    pub unsafe fn run_drops_since(&mut self, mark: ArenaMark) {
+        while self.drops.get_mut().len() > mark.drops {
+            let entry = self.drops.get_mut().pop().unwrap();
+            unsafe { (entry.drop)(entry.ptr) }
+        }
+    }
+
+    /// Drop and free everything allocated since `mark` was created
+    ///
+    /// # Safety
+    ///
+    /// Nothing allocated since `mark` may be used after this call
+    pub unsafe fn reset(&mut self, mark: ArenaMark) {
+        unsafe { self.run_drops_since(mark) };
+        self.used.set(mark.used);
+        self.reserved.set(mark.reserved);
+        self.scopes.get_mut().truncate(mark.scopes);
+    }
+
+    pub fn bytes_used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// The number of bytes reserved from the bump allocators, including the unused
+    /// parts of chunks
+    pub fn bytes_reserved(&self) -> usize {
+        self.reserved.get()
+    }
+
+    /// Drop the value at `ptr` when the arena is dropped
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid `T` allocated in this arena, which isn't dropped by
+    /// anyone else.
+    pub unsafe fn register_drop<T>(&self, ptr: NonNull<T>) {
+        if core::mem::needs_drop::<T>() {
+            let drops = unsafe { &mut *self.drops.get() };
+            drops.push(DropEntry {
+                ptr: ptr.cast(),
+                drop: drop_erased::<T>,
+            });
+        }
+    }
+
+    /// Run all registered destructors, in the reverse order they were registered
+    pub fn run_drops(&self) {
+        // don't hold a borrow of the registry while running a destructor, in case it
+        // allocates into the arena
+        while let Some(entry) = unsafe { (*self.drops.get()).pop() } {
+            unsafe { (entry.drop)(entry.ptr) }
+        }
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        self.run_drops();
+    }
+}
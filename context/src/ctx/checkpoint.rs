@@ -0,0 +1,212 @@
+use std::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use super::{arena::ArenaMark, OwnedContext};
+
+/// A point in the history of an [`OwnedContext`] that it can be rolled back to
+///
+/// Created by [`OwnedContext::checkpoint`], and consumed by either
+/// [`OwnedContext::rollback`] or [`OwnedContext::commit`].
+///
+/// Only an [`OwnedContext`] has checkpoints: a rollback takes it by `&mut`, which
+/// guarantees that no [`Context`](crate::Context) handed out by
+/// [`OwnedContext::with`] is still around to use the discarded types.
+#[must_use = "a checkpoint should be rolled back or committed"]
+#[derive(Debug)]
+pub struct Checkpoint {
+    owner: *const (),
+    serial: u64,
+}
+
+/// The serial of the next checkpoint, shared by every context so a checkpoint can't
+/// match a later context that reuses the address of the one it came from
+static NEXT_SERIAL: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+pub(super) struct Checkpoints {
+    stack: UnsafeCell<Vec<CheckpointData>>,
+}
+
+struct CheckpointData {
+    serial: u64,
+    types: usize,
+    symbols: usize,
+    attribute_log: usize,
+    arena: ArenaMark,
+}
+
+impl Checkpoints {
+    /// Remove `checkpoint` and every checkpoint created after it
+    fn pop(&self, checkpoint: Checkpoint) -> CheckpointData {
+        let stack = unsafe { &mut *self.stack.get() };
+        let Some(index) = stack
+            .iter()
+            .rposition(|data| data.serial == checkpoint.serial)
+        else {
+            panic!("checkpoint was already rolled back or committed")
+        };
+
+        stack.drain(index..).next().unwrap()
+    }
+
+    /// The number of types when the newest remaining checkpoint was created, or 0 if
+    /// there is none
+    fn newest_types(&self) -> usize {
+        let stack = unsafe { &*self.stack.get() };
+        stack.last().map_or(0, |data| data.types)
+    }
+}
+
+impl OwnedContext {
+    /// Record the current state of the context
+    ///
+    /// Everything created after this point, including types, symbols and arena
+    /// allocations, can be discarded with [`OwnedContext::rollback`].
+    pub fn checkpoint(&self) -> Checkpoint {
+        let data = unsafe { self.data.as_ref() };
+        let checkpoints = &data.checkpoints;
+        let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+
+        let stack = unsafe { &mut *checkpoints.stack.get() };
+        stack.push(CheckpointData {
+            serial,
+            types: data.ty.len(),
+            symbols: data.symbols.len(),
+            attribute_log: data.ty.attribute_log_len(),
+            arena: data.arena.mark(),
+        });
+        // attributes may be changed on types that already exist, so the previous
+        // attributes are kept until this checkpoint is gone
+        data.ty.log_attributes_below(data.ty.len());
+
+        Checkpoint {
+            owner: self.data.as_ptr().cast_const().cast(),
+            serial,
+        }
+    }
+
+    /// Keep everything created since `checkpoint`
+    ///
+    /// Checkpoints created after `checkpoint` are committed too.
+    pub fn commit(&self, checkpoint: Checkpoint) {
+        self.assert_owns(&checkpoint);
+        let data = unsafe { self.data.as_ref() };
+        data.checkpoints.pop(checkpoint);
+        data.ty
+            .log_attributes_below(data.checkpoints.newest_types());
+    }
+
+    /// Discard everything created since `checkpoint`
+    ///
+    /// Values allocated with a destructor since then are dropped, and checkpoints
+    /// created after `checkpoint` can no longer be used. This takes `&mut self`, so no
+    /// call to [`OwnedContext::with`] can be running, and no discarded type can be
+    /// used afterwards.
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.assert_owns(&checkpoint);
+        let data = unsafe { self.data.as_mut() };
+        let checkpoint = data.checkpoints.pop(checkpoint);
+
+        // run destructors first, they may still refer to the discarded types
+        unsafe { data.arena.run_drops_since(checkpoint.arena) };
+        data.ty.undo_attributes(checkpoint.attribute_log);
+        data.ty
+            .log_attributes_below(data.checkpoints.newest_types());
+        data.ty.truncate(checkpoint.types);
+        data.symbols.truncate(checkpoint.symbols);
+        unsafe { data.arena.reset(checkpoint.arena) };
+    }
+
+    fn assert_owns(&self, checkpoint: &Checkpoint) {
+        assert!(
+            core::ptr::eq(checkpoint.owner, self.data.as_ptr().cast_const().cast()),
+            "checkpoint belongs to a different context"
+        );
+    }
+}
+
+#[test]
+fn test_rollback() {
+    let mut owned = OwnedContext::new(crate::TEST_TARGET_SPEC);
+    let before = owned.with(|ctx| ctx.stats().type_count());
+
+    let checkpoint = owned.checkpoint();
+    owned.with(|ctx| {
+        let field = crate::types::AggregateField::new(ctx.symbol("x"), ctx.int_ty(24));
+        let point = ctx.create_aggregate("point", [field]);
+        ctx.function(point, &[]);
+    });
+    owned.rollback(checkpoint);
+
+    owned.with(|ctx| {
+        assert_eq!(ctx.stats().type_count(), before);
+        assert_eq!(ctx.stats().int_cache.len, 0);
+        assert!(ctx.get_aggregate("point").is_none());
+        assert!(ctx.get_symbol("x").is_none());
+
+        // the name can be reused after the rollback
+        ctx.create_aggregate("point", []);
+    });
+
+    let outer = owned.checkpoint();
+    let inner = owned.checkpoint();
+    owned.with(|ctx| {
+        ctx.int_ty(24);
+    });
+    owned.commit(inner);
+    owned.rollback(outer);
+    owned.with(|ctx| assert_eq!(ctx.stats().int_cache.len, 0));
+}
+
+#[test]
+fn test_rollback_attributes() {
+    use crate::types::{AttributeValue, Attributes};
+
+    let mut owned = OwnedContext::new(crate::TEST_TARGET_SPEC);
+    owned.with(|ctx| {
+        ctx.update_attributes(ctx.int_ty(32), |attrs| {
+            attrs.doc = Some(ctx.symbol("before"))
+        });
+    });
+
+    // change attributes of types that exist before the checkpoint, referring to symbols
+    // and types that the rollback discards
+    let checkpoint = owned.checkpoint();
+    owned.with(|ctx| {
+        let after = ctx.create_aggregate("after", []);
+        ctx.set_attributes(
+            ctx.int_ty(32),
+            Attributes {
+                doc: Some(ctx.symbol("after")),
+                custom: vec![(ctx.symbol("ty"), AttributeValue::Type(after))],
+                ..Attributes::default()
+            },
+        );
+        ctx.update_attributes(ctx.int_ty(64), |attrs| {
+            attrs.source_name = Some(ctx.symbol("long"))
+        });
+    });
+    owned.rollback(checkpoint);
+
+    owned.with(|ctx| {
+        let attrs = ctx.attributes(ctx.int_ty(32)).unwrap();
+        assert_eq!(attrs.doc.map(|doc| doc.as_str()), Some("before"));
+        assert!(attrs.custom.is_empty());
+        assert_eq!(ctx.attributes(ctx.int_ty(64)), None);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_stale_checkpoint() {
+    let owned = OwnedContext::new(crate::TEST_TARGET_SPEC);
+    let checkpoint = owned.checkpoint();
+    drop(owned);
+
+    // the new context may be allocated where the old one was
+    let mut owned = OwnedContext::new(crate::TEST_TARGET_SPEC);
+    let _ = owned.checkpoint();
+    owned.rollback(checkpoint);
+}
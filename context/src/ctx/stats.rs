@@ -0,0 +1,154 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::types::{AggregateTy, FuncTy, TypeKind};
+
+/// The number of entries in [`ContextStats::largest_aggregates`] and
+/// [`ContextStats::largest_functions`]
+pub const LARGEST_TYPES: usize = 10;
+
+/// A snapshot of the memory used by a context and the types in it
+#[derive(Debug, Clone)]
+pub struct ContextStats<'ctx> {
+    /// The number of bytes allocated in the arena, not counting alignment padding
+    pub arena_bytes_used: usize,
+    /// The number of bytes the arena has reserved, including space it hasn't handed
+    /// out yet
+    pub arena_bytes_reserved: usize,
+    types_by_kind: [usize; TypeKind::ALL.len()],
+    pub int_cache: CacheStats,
+    pub func_cache: CacheStats,
+    /// The aggregates with the most fields, largest first
+    pub largest_aggregates: Vec<AggregateTy<'ctx>>,
+    /// The functions with the most arguments, largest first
+    pub largest_functions: Vec<FuncTy<'ctx>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl CacheStats {
+    /// The fraction of the cache's capacity that is in use
+    pub fn load_factor(&self) -> f64 {
+        if self.capacity == 0 {
+            0.0
+        } else {
+            self.len as f64 / self.capacity as f64
+        }
+    }
+}
+
+impl ContextStats<'_> {
+    /// The total number of types in the context
+    pub fn type_count(&self) -> usize {
+        self.types_by_kind.iter().sum()
+    }
+
+    /// The number of types of the given kind in the context
+    pub fn type_count_of(&self, kind: TypeKind) -> usize {
+        self.types_by_kind[kind as usize]
+    }
+}
+
+/// The indices of the [`LARGEST_TYPES`] largest types pushed, in a min-heap so the
+/// smallest is dropped as soon as there are too many
+#[derive(Default)]
+struct Largest {
+    /// Types of the same size are ordered by index, so the earliest created are kept
+    heap: BinaryHeap<Reverse<(usize, Reverse<u32>)>>,
+}
+
+impl Largest {
+    fn push(&mut self, size: usize, index: u32) {
+        self.heap.push(Reverse((size, Reverse(index))));
+        if self.heap.len() > LARGEST_TYPES {
+            self.heap.pop();
+        }
+    }
+
+    /// The indices of the largest types, largest first
+    fn into_indices(self) -> impl Iterator<Item = u32> {
+        // sorting the reversed keys ascending puts the largest first
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((_, Reverse(index)))| index)
+    }
+}
+
+impl<'ctx> super::Context<'ctx> {
+    pub fn stats(self) -> ContextStats<'ctx> {
+        let type_ctx = self.type_ctx();
+
+        let mut types_by_kind = [0; TypeKind::ALL.len()];
+        let mut largest_aggregates = Largest::default();
+        let mut largest_functions = Largest::default();
+
+        for ty in type_ctx.iter_types() {
+            types_by_kind[ty.kind() as usize] += 1;
+
+            if let Some(aggregate) = ty.try_cast::<AggregateTy>() {
+                largest_aggregates.push(aggregate.fields().len(), ty.index());
+            } else if let Some(func) = ty.try_cast::<FuncTy>() {
+                largest_functions.push(func.args().len(), ty.index());
+            }
+        }
+
+        let type_at = |index| type_ctx.type_at(index).unwrap();
+        let largest_aggregates = largest_aggregates
+            .into_indices()
+            .map(|index| type_at(index).cast::<AggregateTy>())
+            .collect();
+        let largest_functions = largest_functions
+            .into_indices()
+            .map(|index| type_at(index).cast::<FuncTy>())
+            .collect();
+
+        ContextStats {
+            arena_bytes_used: self.0.as_ref().arena.bytes_used(),
+            arena_bytes_reserved: self.0.as_ref().arena.bytes_reserved(),
+            types_by_kind,
+            int_cache: type_ctx.int_cache_stats(),
+            func_cache: type_ctx.func_cache_stats(),
+            largest_aggregates,
+            largest_functions,
+        }
+    }
+}
+
+#[test]
+fn test_stats() {
+    super::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let before = ctx.stats();
+
+        let int = ctx.int_ty(32);
+        ctx.function(int, &[]);
+        let binary = ctx.function(int, &[int, int]);
+        ctx.int_ty(24);
+
+        let after = ctx.stats();
+        assert_eq!(after.type_count(), before.type_count() + 3);
+        assert_eq!(after.type_count_of(TypeKind::Func), 2);
+        assert_eq!(after.int_cache.len, before.int_cache.len + 1);
+        assert_eq!(after.func_cache.len, 2);
+        assert_eq!(after.largest_functions[0], binary.cast::<FuncTy>());
+        assert!(after.arena_bytes_used > before.arena_bytes_used);
+        assert!(after.arena_bytes_reserved >= after.arena_bytes_used);
+
+        // only the largest are kept, and ties keep the earliest created
+        let field = || crate::types::AggregateField::new(ctx.symbol("x"), int);
+        let aggregates = (0..LARGEST_TYPES + 2)
+            .map(|i| ctx.create_aggregate(&format!("a{i}"), [field()]))
+            .collect::<Vec<_>>();
+        let pair = ctx.create_aggregate("pair", [field(), field()]);
+        let largest = ctx.stats().largest_aggregates;
+        assert_eq!(largest.len(), LARGEST_TYPES);
+        assert_eq!(largest[0].erase(), pair);
+        assert!(largest[1..]
+            .iter()
+            .zip(&aggregates)
+            .all(|(&a, &b)| a.erase() == b));
+    });
+}
@@ -1,15 +1,23 @@
 use std::{
-    cell::UnsafeCell,
+    cell::{Cell, UnsafeCell},
     collections::HashMap,
     hash::{BuildHasherDefault, Hash, Hasher},
     num::NonZeroU16,
 };
 
-use crate::{types, TargetSpec};
+use crate::{types, Symbol, TargetSpec};
 
 use super::AllocContext;
 
+mod generic;
+mod namespace;
+
+pub use namespace::Namespace;
+
 pub(super) struct TypeContextData<'ctx> {
+    /// Every type in the context, in the order they were created
+    registry: UnsafeCell<Vec<types::Type<'ctx>>>,
+
     pub unit: types::UnitTy<'ctx>,
     pub int1: types::IntTy<'ctx>,
     pub int8: types::IntTy<'ctx>,
@@ -27,10 +35,156 @@ pub(super) struct TypeContextData<'ctx> {
     pub ieee32: types::FloatTy<'ctx>,
     pub ieee64: types::FloatTy<'ctx>,
     pub ieee128: types::FloatTy<'ctx>,
+    pub x87: types::FloatTy<'ctx>,
     pub ptr: types::PointerTy<'ctx>,
-
-    aggregate_cache: UnsafeCell<istr::IBytesMap<types::AggregateTy<'ctx>>>,
+    /// Pointers into address spaces other than 0
+    ptr_cache:
+        UnsafeCell<HashMap<u32, types::PointerTy<'ctx>, BuildHasherDefault<rustc_hash::FxHasher>>>,
+
+    /// Aggregates, opaque types and aliases, by their full path
+    named: UnsafeCell<
+        HashMap<Symbol<'ctx>, types::Type<'ctx>, BuildHasherDefault<rustc_hash::FxHasher>>,
+    >,
+    /// The named types declared directly in each namespace, in creation order
+    namespaces: UnsafeCell<
+        HashMap<Namespace<'ctx>, Vec<types::Type<'ctx>>, BuildHasherDefault<rustc_hash::FxHasher>>,
+    >,
     func_cache: UnsafeCell<hashbrown::HashTable<types::FuncTy<'ctx>>>,
+    fn_ptr_cache: UnsafeCell<
+        HashMap<
+            types::FuncTy<'ctx>,
+            types::FnPointerTy<'ctx>,
+            BuildHasherDefault<rustc_hash::FxHasher>,
+        >,
+    >,
+
+    param_cache:
+        UnsafeCell<HashMap<u32, types::ParamTy<'ctx>, BuildHasherDefault<rustc_hash::FxHasher>>>,
+    /// Instances of generic aggregates, hashed by the generic and its arguments
+    instance_cache: UnsafeCell<hashbrown::HashTable<generic::Instance<'ctx>>>,
+    /// The generic and arguments that each instance was created from
+    instances: UnsafeCell<
+        HashMap<
+            types::AggregateTy<'ctx>,
+            generic::Instance<'ctx>,
+            BuildHasherDefault<rustc_hash::FxHasher>,
+        >,
+    >,
+
+    attributes: UnsafeCell<types::TypeMap<'ctx, types::Attributes<'ctx>>>,
+    /// The attributes that types created before the newest checkpoint had before each
+    /// change, so a rollback can restore them
+    attribute_log: UnsafeCell<Vec<(types::Type<'ctx>, Option<types::Attributes<'ctx>>)>>,
+    /// Changes to the attributes of types with a lower index are logged
+    logged_below: Cell<usize>,
+    /// Pointer maps by type and the address space they were filtered to, boxed so
+    /// references to them stay valid as the cache grows
+    pointer_maps: UnsafeCell<
+        HashMap<
+            (types::Type<'ctx>, Option<u32>),
+            Box<types::PointerMap>,
+            BuildHasherDefault<rustc_hash::FxHasher>,
+        >,
+    >,
+}
+
+impl TypeContextData<'_> {
+    /// Remove every type created after the first `len` from the registry and caches
+    pub(super) fn truncate(&mut self, len: usize) {
+        let is_old = |index: u32| (index as usize) < len;
+
+        self.int_cache.get_mut().retain(|_, ty| is_old(ty.index()));
+        self.ptr_cache.get_mut().retain(|_, ty| is_old(ty.index()));
+        self.pointer_maps
+            .get_mut()
+            .retain(|(ty, _), _| is_old(ty.index()));
+        self.named.get_mut().retain(|_, ty| is_old(ty.index()));
+        // a namespace's path points into the name of its oldest member, so it is only
+        // removed along with all of its members
+        self.namespaces.get_mut().retain(|_, members| {
+            members.retain(|ty| is_old(ty.index()));
+            !members.is_empty()
+        });
+        self.func_cache.get_mut().retain(|ty| is_old(ty.index()));
+        self.fn_ptr_cache
+            .get_mut()
+            .retain(|_, ty| is_old(ty.index()));
+        self.param_cache
+            .get_mut()
+            .retain(|_, ty| is_old(ty.index()));
+        self.instance_cache
+            .get_mut()
+            .retain(|instance| is_old(instance.ty.index()));
+        self.instances.get_mut().retain(|ty, _| is_old(ty.index()));
+        self.attributes.get_mut().truncate(len);
+        self.registry.get_mut().truncate(len);
+    }
+
+    pub(super) fn len(&self) -> usize {
+        unsafe { (*self.registry.get()).len() }
+    }
+
+    pub(super) fn attribute_log_len(&self) -> usize {
+        unsafe { (*self.attribute_log.get()).len() }
+    }
+
+    /// Log changes to the attributes of the first `len` types, or stop logging and
+    /// forget the log if `len` is 0
+    pub(super) fn log_attributes_below(&self, len: usize) {
+        self.logged_below.set(len);
+        if len == 0 {
+            unsafe { (*self.attribute_log.get()).clear() };
+        }
+    }
+
+    /// Restore the attributes changed since the log had `log_len` entries
+    ///
+    /// The attributes set since then may refer to symbols and types that are about to
+    /// be discarded, so this must run before they are.
+    pub(super) fn undo_attributes(&mut self, log_len: usize) {
+        let attributes = self.attributes.get_mut();
+        for (ty, previous) in self.attribute_log.get_mut().drain(log_len..).rev() {
+            match previous {
+                Some(previous) => attributes.insert(ty, previous),
+                None => attributes.remove(ty),
+            };
+        }
+    }
+}
+
+/// Give `ty` the next index in the context, and record it in the registry
+fn register<'ctx, T: ?Sized>(
+    registry: &UnsafeCell<Vec<types::Type<'ctx>>>,
+    ty: types::RawType<'ctx, T>,
+) -> types::RawType<'ctx, T> {
+    let registry = unsafe { &mut *registry.get() };
+    let index = u32::try_from(registry.len()).expect("too many types in the context");
+    ty.header().set_index(index);
+    registry.push(ty.erase());
+    ty
+}
+
+fn init_registered<'ctx, 'a, T: ?Sized, I>(
+    registry: &'a UnsafeCell<Vec<types::Type<'ctx>>>,
+    init: I,
+) -> impl init::Initializer<types::RawType<'ctx, T>, Error = I::Error> + 'a
+where
+    I: init::Initializer<types::RawType<'ctx, T>> + 'a,
+{
+    init::try_init_fn(move |ptr: init::ptr::Uninit<types::RawType<'ctx, T>>| {
+        let ptr = ptr.try_init(init)?;
+        register(registry, *ptr);
+        Ok(ptr)
+    })
+}
+
+fn hash_signature(ret: types::Type, args: &[types::Type]) -> u64 {
+    let mut hasher = rustc_hash::FxHasher::default();
+    ret.index().hash(&mut hasher);
+    for arg in args {
+        arg.index().hash(&mut hasher);
+    }
+    hasher.finish()
 }
 
 impl<'ctx> super::TypeContext<'ctx> {
@@ -38,6 +192,77 @@ impl<'ctx> super::TypeContext<'ctx> {
         super::ContextId(super::PhantomData)
     }
 
+    /// The type with the given [`index`](types::Type::index), if there is one
+    pub fn type_at(self, index: u32) -> Option<types::Type<'ctx>> {
+        let registry = unsafe { &*self.0.as_ref().registry.get() };
+        registry.get(index as usize).copied()
+    }
+
+    /// Every type in the context, in the order they were created
+    ///
+    /// Types created while iterating aren't included.
+    pub fn iter_types(self) -> impl Iterator<Item = types::Type<'ctx>> {
+        let len = unsafe { (*self.0.as_ref().registry.get()).len() };
+        (0..len).map(move |index| unsafe { (*self.0.as_ref().registry.get())[index] })
+    }
+
+    pub(super) fn int_cache_stats(self) -> super::CacheStats {
+        let cache = unsafe { &*self.0.as_ref().int_cache.get() };
+        super::CacheStats {
+            len: cache.len(),
+            capacity: cache.capacity(),
+        }
+    }
+
+    pub(super) fn func_cache_stats(self) -> super::CacheStats {
+        let cache = unsafe { &*self.0.as_ref().func_cache.get() };
+        super::CacheStats {
+            len: cache.len(),
+            capacity: cache.capacity(),
+        }
+    }
+
+    /// The attributes attached to `ty`, if any were set
+    pub fn attributes(self, ty: types::Type<'ctx>) -> Option<types::Attributes<'ctx>> {
+        let attributes = unsafe { &*self.0.as_ref().attributes.get() };
+        attributes.get(ty).cloned()
+    }
+
+    pub fn set_attributes(self, ty: types::Type<'ctx>, attrs: types::Attributes<'ctx>) {
+        let data = self.0.as_ref();
+        let attributes = unsafe { &mut *data.attributes.get() };
+        let previous = attributes.insert(ty, attrs);
+
+        if (ty.index() as usize) < data.logged_below.get() {
+            let log = unsafe { &mut *data.attribute_log.get() };
+            log.push((ty, previous));
+        }
+    }
+
+    /// Modify the attributes attached to `ty`, starting from the defaults if none were
+    /// set yet
+    pub fn update_attributes(
+        self,
+        ty: types::Type<'ctx>,
+        f: impl FnOnce(&mut types::Attributes<'ctx>),
+    ) {
+        // copy the attributes out of the store, so `f` is free to use the context
+        let mut attrs = self.attributes(ty).unwrap_or_default();
+        f(&mut attrs);
+        self.set_attributes(ty, attrs);
+    }
+
+    /// Every aggregate in the context, sorted by name
+    pub fn iter_aggregates(self) -> impl Iterator<Item = types::AggregateTy<'ctx>> {
+        let cache = unsafe { &*self.0.as_ref().named.get() };
+        let aggregates = cache
+            .values()
+            .filter_map(|ty| ty.try_cast::<types::AggregateTy>());
+        let mut aggregates = aggregates.collect::<Vec<_>>();
+        aggregates.sort_unstable_by_key(|ty| ty.name());
+        aggregates.into_iter()
+    }
+
     pub const fn unit(self) -> types::UnitTy<'ctx> {
         self.0.as_ref().unit
     }
@@ -46,6 +271,49 @@ impl<'ctx> super::TypeContext<'ctx> {
         self.0.as_ref().ptr
     }
 
+    /// The pointer type for `address_space`
+    pub fn pointer_in(
+        self,
+        alloc: AllocContext<'ctx>,
+        address_space: u32,
+    ) -> types::PointerTy<'ctx> {
+        let ty = self.0.as_ref();
+        if address_space == 0 {
+            return ty.ptr;
+        }
+
+        let cache = unsafe { &mut *ty.ptr_cache.get() };
+        *cache.entry(address_space).or_insert_with(|| {
+            let value = init::try_init_on_stack(types::PointerTy::init(address_space, alloc))
+                .unwrap_or_else(|inf| match inf {});
+            register(&ty.registry, value)
+        })
+    }
+
+    pub(crate) fn get_pointer_map(
+        self,
+        ty: types::Type<'ctx>,
+        address_space: Option<u32>,
+    ) -> Option<&'ctx types::PointerMap> {
+        let cache = unsafe { &*self.0.as_ref().pointer_maps.get() };
+        let map = cache.get(&(ty, address_space))?;
+        // entries are only removed by `truncate`, which needs exclusive access
+        Some(unsafe { &*core::ptr::addr_of!(**map) })
+    }
+
+    pub(crate) fn insert_pointer_map(
+        self,
+        ty: types::Type<'ctx>,
+        address_space: Option<u32>,
+        map: types::PointerMap,
+    ) -> &'ctx types::PointerMap {
+        let cache = unsafe { &mut *self.0.as_ref().pointer_maps.get() };
+        let map = cache
+            .entry((ty, address_space))
+            .or_insert_with(|| Box::new(map));
+        unsafe { &*core::ptr::addr_of!(**map) }
+    }
+
     #[inline]
     pub fn int(self, alloc: AllocContext<'ctx>, bits: NonZeroU16) -> types::IntTy<'ctx> {
         let ty = self.0.as_ref();
@@ -65,11 +333,13 @@ impl<'ctx> super::TypeContext<'ctx> {
     fn int_slow(self, alloc: AllocContext<'ctx>, bits: NonZeroU16) -> types::IntTy<'ctx> {
         assert!(!bits.get().is_power_of_two() || bits.get() > 256);
 
-        let cache = unsafe { &mut *self.0.as_ref().int_cache.get() };
+        let ty = self.0.as_ref();
+        let cache = unsafe { &mut *ty.int_cache.get() };
 
         *cache.entry(bits.get()).or_insert_with(|| {
-            init::try_init_on_stack(types::IntTy::init(bits.get(), alloc))
-                .unwrap_or_else(|inf| match inf {})
+            let value = init::try_init_on_stack(types::IntTy::init(bits.get(), alloc))
+                .unwrap_or_else(|inf| match inf {});
+            register(&ty.registry, value)
         })
     }
 
@@ -81,48 +351,177 @@ impl<'ctx> super::TypeContext<'ctx> {
             types::FloatKind::Ieee32Bit => ty.ieee32,
             types::FloatKind::Ieee64Bit => ty.ieee64,
             types::FloatKind::Ieee128Bit => ty.ieee128,
+            types::FloatKind::X87Extended80Bit => ty.x87,
         }
     }
 
-    pub fn get_aggregate(self, name: istr::IBytes) -> Option<types::AggregateTy<'ctx>> {
-        let cache = self.0.as_ref().aggregate_cache.get();
+    /// The integer type with the size of a pointer
+    #[inline]
+    pub const fn intptr(self) -> types::IntTy<'ctx> {
+        self.0.as_ref().intptr
+    }
+
+    /// The integer type with the size of the difference between two pointers
+    #[inline]
+    pub const fn intptr_diff(self) -> types::IntTy<'ctx> {
+        self.0.as_ref().intptr_diff
+    }
+
+    /// The representation of `ty` under `model`
+    pub fn c_type(
+        self,
+        alloc: AllocContext<'ctx>,
+        model: &crate::CDataModel,
+        ty: crate::CType,
+    ) -> types::Type<'ctx> {
+        let int = |bits| self.int(alloc, NonZeroU16::new(bits).unwrap()).erase();
+
+        match ty {
+            crate::CType::Bool
+            | crate::CType::Char
+            | crate::CType::SignedChar
+            | crate::CType::UnsignedChar => int(8),
+            crate::CType::Short | crate::CType::UnsignedShort => int(model.short_bits),
+            crate::CType::Int | crate::CType::UnsignedInt => int(model.int_bits),
+            crate::CType::Long | crate::CType::UnsignedLong => int(model.long_bits),
+            crate::CType::LongLong | crate::CType::UnsignedLongLong => int(model.long_long_bits),
+            crate::CType::WChar => int(model.wchar_bits),
+            crate::CType::Size | crate::CType::IntPtr | crate::CType::UIntPtr => {
+                self.intptr().erase()
+            }
+            crate::CType::PtrDiff => self.intptr_diff().erase(),
+            crate::CType::Float => self.float(types::FloatKind::Ieee32Bit).erase(),
+            crate::CType::Double => self.float(types::FloatKind::Ieee64Bit).erase(),
+            crate::CType::LongDouble => self.float(model.long_double).erase(),
+        }
+    }
+
+    /// The aggregate, opaque type or alias with the full path `name`
+    pub fn get_named(self, name: Symbol<'ctx>) -> Option<types::Type<'ctx>> {
+        let cache = self.0.as_ref().named.get();
         let cache = unsafe { &*cache };
 
         cache.get(&name).copied()
     }
 
-    pub fn aggregate(self, name: istr::IBytes) -> types::AggregateTy<'ctx> {
-        let cache = self.0.as_ref().aggregate_cache.get();
+    pub fn get_aggregate(self, name: Symbol<'ctx>) -> Option<types::AggregateTy<'ctx>> {
+        self.get_named(name)?.try_cast::<types::AggregateTy>()
+    }
+
+    pub fn aggregate(self, name: Symbol<'ctx>) -> types::AggregateTy<'ctx> {
+        let cache = self.0.as_ref().named.get();
         let cache = unsafe { &*cache };
 
-        cache[&name]
+        cache[&name].cast::<types::AggregateTy>()
+    }
+
+    /// Create a type called `name` without a body
+    pub fn create_opaque(
+        self,
+        alloc: AllocContext<'ctx>,
+        name: Symbol<'ctx>,
+    ) -> types::OpaqueTy<'ctx> {
+        debug_assert!(self.get_named(name).is_none());
+
+        let value = init::try_init_on_stack(types::OpaqueTy::init(name, alloc))
+            .unwrap_or_else(|inf| match inf {});
+        let value = register(&self.0.as_ref().registry, value);
+        self.insert_named(name, value.erase());
+        value
+    }
+
+    /// Create `name` as another name for `aliased`
+    pub fn create_alias(
+        self,
+        alloc: AllocContext<'ctx>,
+        name: Symbol<'ctx>,
+        aliased: types::Type<'ctx>,
+    ) -> types::AliasTy<'ctx> {
+        debug_assert!(self.get_named(name).is_none());
+
+        let value = init::try_init_on_stack(types::AliasTy::init((name, aliased), alloc))
+            .unwrap_or_else(|inf| match inf {});
+        let value = register(&self.0.as_ref().registry, value);
+        self.insert_named(name, value.erase());
+        value
+    }
+
+    fn insert_named(self, name: Symbol<'ctx>, ty: types::Type<'ctx>) {
+        let cache = unsafe { &mut *self.0.as_ref().named.get() };
+        cache.insert(name, ty);
+
+        let (namespace, _) = Namespace::split(name.as_str());
+        let namespaces = unsafe { &mut *self.0.as_ref().namespaces.get() };
+        namespaces.entry(namespace).or_default().push(ty);
     }
 
     pub fn create_aggregate<I: IntoIterator<Item = types::AggregateField<'ctx>>>(
         self,
         alloc: AllocContext<'ctx>,
-        name: istr::IBytes,
+        name: Symbol<'ctx>,
+        fields: I,
+    ) -> types::AggregateTy<'ctx>
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_aggregate_with(alloc, name, types::AggregateRepr::default(), fields)
+    }
+
+    pub fn create_aggregate_with<I: IntoIterator<Item = types::AggregateField<'ctx>>>(
+        self,
+        alloc: AllocContext<'ctx>,
+        name: Symbol<'ctx>,
+        repr: types::AggregateRepr,
+        fields: I,
+    ) -> types::AggregateTy<'ctx>
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.create_generic_aggregate(alloc, name, 0, repr, fields)
+    }
+
+    /// Create an aggregate with `params` type parameters, its fields may refer to them
+    /// with [`TypeContext::param`]
+    pub fn create_generic_aggregate<I: IntoIterator<Item = types::AggregateField<'ctx>>>(
+        self,
+        alloc: AllocContext<'ctx>,
+        name: Symbol<'ctx>,
+        params: u32,
+        repr: types::AggregateRepr,
         fields: I,
     ) -> types::AggregateTy<'ctx>
     where
         I::IntoIter: ExactSizeIterator,
     {
-        debug_assert!(self.get_aggregate(name).is_none());
+        debug_assert!(self.get_named(name).is_none());
 
+        let value = self.alloc_aggregate(alloc, name, params, repr, fields);
+        self.insert_named(name, value.erase());
+        value
+    }
+
+    /// Create an aggregate without adding it to the namespace
+    fn alloc_aggregate<I: IntoIterator<Item = types::AggregateField<'ctx>>>(
+        self,
+        alloc: AllocContext<'ctx>,
+        name: Symbol<'ctx>,
+        params: u32,
+        repr: types::AggregateRepr,
+        fields: I,
+    ) -> types::AggregateTy<'ctx>
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
         let value = init::try_init_on_stack(types::AggregateTy::init_with::<
             _,
             types::AggregateLayoutProvider,
         >(
-            types::AggregateTy::init_data(name, fields), alloc
+            types::AggregateTy::init_data(name, repr, params, fields),
+            alloc,
         ))
         .expect("Invalid implementation of ExactSizeIterator");
 
-        let cache = self.0.as_ref().aggregate_cache.get();
-        let cache = unsafe { &mut *cache };
-
-        cache.insert(name, value);
-
-        value
+        register(&self.0.as_ref().registry, value)
     }
 
     pub fn function(
@@ -133,10 +532,7 @@ impl<'ctx> super::TypeContext<'ctx> {
     ) -> types::FuncTy<'ctx> {
         let ty = self.0.as_ref();
         let cache = unsafe { &*ty.func_cache.get() };
-        let mut hasher = rustc_hash::FxHasher::default();
-        ret.hash(&mut hasher);
-        args.hash(&mut hasher);
-        let hash = hasher.finish();
+        let hash = hash_signature(ret, args);
 
         if let Some(func) = cache.find(hash, |t| t.ret() == ret && t.args() == args) {
             return *func;
@@ -164,13 +560,25 @@ impl<'ctx> super::TypeContext<'ctx> {
         let cache = unsafe { &mut *ty.func_cache.get() };
 
         cache.insert_unique(hash, value, |value| {
-            let mut hasher = rustc_hash::FxHasher::default();
-            value.ret().hash(&mut hasher);
-            value.args().hash(&mut hasher);
-            hasher.finish()
+            hash_signature(value.ret(), value.args())
         });
 
-        value
+        register(&ty.registry, value)
+    }
+
+    pub fn fn_pointer(
+        self,
+        alloc: AllocContext<'ctx>,
+        func: types::FuncTy<'ctx>,
+    ) -> types::FnPointerTy<'ctx> {
+        let ty = self.0.as_ref();
+        let cache = unsafe { &mut *ty.fn_ptr_cache.get() };
+
+        *cache.entry(func).or_insert_with(|| {
+            let value = init::try_init_on_stack(types::FnPointerTy::init(func, alloc))
+                .unwrap_or_else(|inf| match inf {});
+            register(&ty.registry, value)
+        })
     }
 }
 
@@ -187,26 +595,28 @@ impl<'ctx> init::Ctor<TypeContextDataArgs<'ctx, '_>> for TypeContextData<'ctx> {
         ptr: init::ptr::Uninit<'a, Self>,
         args: TypeContextDataArgs<'ctx, '_>,
     ) -> Result<init::ptr::Init<'a, Self>, Self::Error> {
-        assert!(args.target.pointer_size_bytes.is_power_of_two());
-        assert!(args.target.pointer_diff_size_bytes.is_power_of_two());
+        // the target is validated by the context's constructors
+        debug_assert!(args.target.validate().is_ok());
 
         let mut int_cache_ = HashMap::default();
 
         init::init_struct! {
             ptr => Self {
-                unit: types::UnitTy::init((), args.alloc),
-                ptr: types::PointerTy::init((), args.alloc),
-                int1: types::IntTy::init(1, args.alloc),
-                int8: types::IntTy::init(8, args.alloc),
-                int16: types::IntTy::init(16, args.alloc),
-                int32: types::IntTy::init(32, args.alloc),
-                int64: types::IntTy::init(64, args.alloc),
-                int128: types::IntTy::init(128, args.alloc),
-                int256: types::IntTy::init(256, args.alloc),
-                ieee16: types::FloatTy::init(types::FloatKind::Ieee16Bit, args.alloc),
-                ieee32: types::FloatTy::init(types::FloatKind::Ieee32Bit, args.alloc),
-                ieee64: types::FloatTy::init(types::FloatKind::Ieee64Bit, args.alloc),
-                ieee128: types::FloatTy::init(types::FloatKind::Ieee128Bit, args.alloc),
+                registry: init::init(UnsafeCell::new(Vec::new())),
+                unit: init_registered(&registry, types::UnitTy::init((), args.alloc)),
+                ptr: init_registered(&registry, types::PointerTy::init(0, args.alloc)),
+                int1: init_registered(&registry, types::IntTy::init(1, args.alloc)),
+                int8: init_registered(&registry, types::IntTy::init(8, args.alloc)),
+                int16: init_registered(&registry, types::IntTy::init(16, args.alloc)),
+                int32: init_registered(&registry, types::IntTy::init(32, args.alloc)),
+                int64: init_registered(&registry, types::IntTy::init(64, args.alloc)),
+                int128: init_registered(&registry, types::IntTy::init(128, args.alloc)),
+                int256: init_registered(&registry, types::IntTy::init(256, args.alloc)),
+                ieee16: init_registered(&registry, types::FloatTy::init(types::FloatKind::Ieee16Bit, args.alloc)),
+                ieee32: init_registered(&registry, types::FloatTy::init(types::FloatKind::Ieee32Bit, args.alloc)),
+                ieee64: init_registered(&registry, types::FloatTy::init(types::FloatKind::Ieee64Bit, args.alloc)),
+                ieee128: init_registered(&registry, types::FloatTy::init(types::FloatKind::Ieee128Bit, args.alloc)),
+                x87: init_registered(&registry, types::FloatTy::init(types::FloatKind::X87Extended80Bit, args.alloc)),
                 intptr: init::init_fn(|ptr| {
                     let arg = match args.target.pointer_size_bytes {
                         1 => *int8,
@@ -217,7 +627,7 @@ impl<'ctx> init::Ctor<TypeContextDataArgs<'ctx, '_>> for TypeContextData<'ctx> {
                         32 => *int256,
                         bytes => {
                             let bits = 8 * u16::from(bytes);
-                            let arg = ptr.init(types::IntTy::init(bits, args.alloc));
+                            let arg = ptr.init(init_registered(&registry, types::IntTy::init(bits, args.alloc)));
                             int_cache_.insert(bits, *arg);
                             return arg
                         }
@@ -229,7 +639,7 @@ impl<'ctx> init::Ctor<TypeContextDataArgs<'ctx, '_>> for TypeContextData<'ctx> {
                     let arg = if args.target.pointer_size_bytes == args.target.pointer_diff_size_bytes {
                         *intptr
                     } else {
-                        match args.target.pointer_size_bytes {
+                        match args.target.pointer_diff_size_bytes {
                             1 => *int8,
                             2 => *int16,
                             4 => *int32,
@@ -238,7 +648,7 @@ impl<'ctx> init::Ctor<TypeContextDataArgs<'ctx, '_>> for TypeContextData<'ctx> {
                             32 => *int256,
                             bytes => {
                                 let bits = 8 * u16::from(bytes);
-                                let arg = ptr.init(types::IntTy::init(bits, args.alloc));
+                                let arg = ptr.init(init_registered(&registry, types::IntTy::init(bits, args.alloc)));
                                 int_cache_.insert(bits, *arg);
                                 return arg
                             }
@@ -248,8 +658,18 @@ impl<'ctx> init::Ctor<TypeContextDataArgs<'ctx, '_>> for TypeContextData<'ctx> {
                     ptr.write(arg)
                 }),
                 int_cache: init::init(UnsafeCell::new(int_cache_)),
-                aggregate_cache: init::init(Default::default()),
+                named: init::init(Default::default()),
+                namespaces: init::init(Default::default()),
                 func_cache: init::init(Default::default()),
+                fn_ptr_cache: init::init(Default::default()),
+                param_cache: init::init(Default::default()),
+                instance_cache: init::init(Default::default()),
+                instances: init::init(Default::default()),
+                attributes: init::init(Default::default()),
+                attribute_log: init::init(Default::default()),
+                logged_below: init::init(Default::default()),
+                ptr_cache: init::init(Default::default()),
+                pointer_maps: init::init(Default::default()),
             }
         }
     }
@@ -260,10 +680,10 @@ fn test_func() {
     super::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
         let arg_ty = ctx.create_aggregate(
             "hello world",
-            [crate::types::AggregateField {
-                name: istr::IBytes::new("field-name".as_bytes()),
-                ty: ctx.pointer_ty(),
-            }],
+            [crate::types::AggregateField::new(
+                ctx.symbol("field-name"),
+                ctx.pointer_ty(),
+            )],
         );
         let a = ctx.function(ctx.pointer_ty(), &[arg_ty]);
         let arg_ty = ctx.get_aggregate("hello world").unwrap();
@@ -271,3 +691,38 @@ fn test_func() {
         assert!(a == b);
     });
 }
+
+#[test]
+fn test_type_index() {
+    let indices = || {
+        super::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+            let int = ctx.int_ty(24);
+            let func = ctx.function(int, &[ctx.pointer_ty()]);
+            assert_eq!(ctx.type_ctx().type_at(func.index()), Some(func));
+            assert_eq!(ctx.type_ctx().type_at(u32::MAX), None);
+            [int.index(), func.index()]
+        })
+    };
+
+    assert_eq!(indices(), indices());
+}
+
+#[test]
+fn test_iter_types() {
+    super::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let singletons = ctx.type_ctx().iter_types().count();
+        let b = ctx.create_aggregate("b", []);
+        let a = ctx.create_aggregate("a", []);
+
+        let types = ctx.type_ctx().iter_types().collect::<Vec<_>>();
+        assert_eq!(types.len(), singletons + 2);
+        assert_eq!(types[singletons..], [b, a]);
+        assert!(types
+            .iter()
+            .enumerate()
+            .all(|(i, ty)| ty.index() as usize == i));
+
+        let aggregates = ctx.type_ctx().iter_aggregates().map(|ty| ty.erase());
+        assert!(aggregates.eq([a, b]));
+    });
+}
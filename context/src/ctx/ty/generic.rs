@@ -0,0 +1,204 @@
+use crate::{ctx::AllocContext, types, TypeContext};
+
+use super::{hash_signature, register};
+
+#[derive(Clone, Copy)]
+pub(in crate::ctx) struct Instance<'ctx> {
+    pub generic: types::AggregateTy<'ctx>,
+    pub args: &'ctx [types::Type<'ctx>],
+    pub ty: types::AggregateTy<'ctx>,
+}
+
+impl<'ctx> TypeContext<'ctx> {
+    /// The placeholder for the type argument at `index`
+    pub fn param(self, alloc: AllocContext<'ctx>, index: u32) -> types::ParamTy<'ctx> {
+        let ty = self.0.as_ref();
+        let cache = unsafe { &mut *ty.param_cache.get() };
+
+        *cache.entry(index).or_insert_with(|| {
+            let value = init::try_init_on_stack(types::ParamTy::init(index, alloc))
+                .unwrap_or_else(|inf| match inf {});
+            register(&ty.registry, value)
+        })
+    }
+
+    /// The generic aggregate and type arguments `ty` was instantiated from, if it is an
+    /// instance
+    pub fn instance_of(
+        self,
+        ty: types::AggregateTy<'ctx>,
+    ) -> Option<(types::AggregateTy<'ctx>, &'ctx [types::Type<'ctx>])> {
+        let instances = unsafe { &*self.0.as_ref().instances.get() };
+        let instance = instances.get(&ty)?;
+        Some((instance.generic, instance.args))
+    }
+
+    /// Substitute `args` for the type parameters in `generic`
+    ///
+    /// If `generic` is a generic aggregate this creates an instance of it, which is only
+    /// created once for each list of arguments. Otherwise every parameter that appears
+    /// in `generic` is replaced.
+    pub fn instantiate(
+        self,
+        alloc: AllocContext<'ctx>,
+        generic: types::Type<'ctx>,
+        args: &[types::Type<'ctx>],
+    ) -> types::Type<'ctx> {
+        match generic.try_cast::<types::AggregateTy>() {
+            Some(aggregate) if aggregate.is_generic() => {
+                self.instantiate_aggregate(alloc, aggregate, args).erase()
+            }
+            _ => self.substitute(alloc, generic, args),
+        }
+    }
+
+    fn instantiate_aggregate(
+        self,
+        alloc: AllocContext<'ctx>,
+        generic: types::AggregateTy<'ctx>,
+        args: &[types::Type<'ctx>],
+    ) -> types::AggregateTy<'ctx> {
+        assert_eq!(
+            args.len(),
+            generic.params() as usize,
+            "wrong number of type arguments for {}",
+            generic.name()
+        );
+
+        let ty = self.0.as_ref();
+        let hash = hash_signature(generic.erase(), args);
+        let cache = unsafe { &*ty.instance_cache.get() };
+
+        if let Some(instance) = cache.find(hash, |instance| {
+            instance.generic == generic && instance.args == args
+        }) {
+            return instance.ty;
+        }
+
+        let fields = generic
+            .fields()
+            .iter()
+            .map(|field| types::AggregateField {
+                ty: self.substitute(alloc, field.ty, args),
+                ..*field
+            })
+            .collect::<Vec<_>>();
+
+        let instance = Instance {
+            generic,
+            args: alloc.alloc_slice_copy(args),
+            ty: self.alloc_aggregate(alloc, generic.name(), 0, generic.repr(), fields),
+        };
+
+        let cache = unsafe { &mut *ty.instance_cache.get() };
+        cache.insert_unique(hash, instance, |instance| {
+            hash_signature(instance.generic.erase(), instance.args)
+        });
+
+        let instances = unsafe { &mut *ty.instances.get() };
+        instances.insert(instance.ty, instance);
+
+        instance.ty
+    }
+
+    fn substitute(
+        self,
+        alloc: AllocContext<'ctx>,
+        ty: types::Type<'ctx>,
+        args: &[types::Type<'ctx>],
+    ) -> types::Type<'ctx> {
+        let substitute = |ty| self.substitute(alloc, ty, args);
+
+        match ty.kind() {
+            types::TypeKind::Param => {
+                let index = ty.cast::<types::ParamTy>().param_index();
+                *args.get(index as usize).unwrap_or_else(|| {
+                    panic!(
+                        "type parameter {index} is out of range, {} type arguments were given",
+                        args.len()
+                    )
+                })
+            }
+            types::TypeKind::Func => {
+                let func = ty.cast::<types::FuncTy>();
+                let ret = substitute(func.ret());
+                let func_args = func.args().iter().map(|&arg| substitute(arg));
+                let func_args = func_args.collect::<Vec<_>>();
+                self.function(alloc, ret, &func_args).erase()
+            }
+            types::TypeKind::FnPointer => {
+                let func = substitute(ty.cast::<types::FnPointerTy>().func().erase());
+                self.fn_pointer(alloc, func.cast::<types::FuncTy>()).erase()
+            }
+            types::TypeKind::Aggregate => {
+                let Some((generic, generic_args)) =
+                    self.instance_of(ty.cast::<types::AggregateTy>())
+                else {
+                    // nominal aggregates can't refer to type parameters
+                    return ty;
+                };
+
+                let generic_args = generic_args.iter().map(|&arg| substitute(arg));
+                let generic_args = generic_args.collect::<Vec<_>>();
+                self.instantiate_aggregate(alloc, generic, &generic_args)
+                    .erase()
+            }
+            // the alias can't name the substituted type, so it is dropped
+            types::TypeKind::Alias => substitute(ty.cast::<types::AliasTy>().aliased()),
+            types::TypeKind::Unit
+            | types::TypeKind::Int
+            | types::TypeKind::Float
+            | types::TypeKind::Pointer
+            | types::TypeKind::Opaque => ty,
+        }
+    }
+}
+
+#[test]
+fn test_instantiate() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+        let t = ctx.param_ty(0);
+
+        let pair = ctx.create_generic_aggregate(
+            "Pair",
+            1,
+            [
+                types::AggregateField::new(ctx.symbol("first"), t),
+                types::AggregateField::new(ctx.symbol("second"), int),
+            ],
+        );
+        assert!(matches!(pair.layout(ctx), Ok(types::Layout::Unknown)));
+
+        let pair_long = ctx.instantiate(pair, &[long]);
+        assert_eq!(pair_long, ctx.instantiate(pair, &[long]));
+        assert_ne!(pair_long, ctx.instantiate(pair, &[int]));
+        match pair_long.layout(ctx).unwrap() {
+            types::Layout::Concrete(layout) => assert_eq!(layout.size(), 16),
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+
+        // instances of other generics are substituted too
+        let pair_t = ctx.instantiate(pair, &[t]);
+        let wrapper = ctx.create_generic_aggregate(
+            "Wrapper",
+            1,
+            [types::AggregateField::new(ctx.symbol("inner"), pair_t)],
+        );
+        let wrapper_long = ctx
+            .instantiate(wrapper, &[long])
+            .cast::<types::AggregateTy>();
+        assert_eq!(wrapper_long.fields()[0].ty, pair_long);
+        assert_eq!(
+            ctx.type_ctx().instance_of(wrapper_long),
+            Some((wrapper.cast::<types::AggregateTy>(), &[long][..]))
+        );
+
+        let generic_fn = ctx.function(t, &[t, ctx.pointer_ty()]);
+        assert_eq!(
+            ctx.instantiate(generic_fn, &[int]),
+            ctx.function(int, &[int, ctx.pointer_ty()])
+        );
+    });
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+
+use crate::{types, TypeContext};
+
+/// A scope that named types are declared in, identified by its path such as `crate::mod`
+///
+/// Named types are registered under their full path, so `crate::mod::Node` is `Node`
+/// in the namespace `crate::mod`. Names without a `::` are in [`Namespace::ROOT`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Namespace<'ctx> {
+    path: &'ctx str,
+}
+
+impl<'ctx> Namespace<'ctx> {
+    pub const ROOT: Self = Self { path: "" };
+
+    pub(in crate::ctx) const fn new(path: &'ctx str) -> Self {
+        Self { path }
+    }
+
+    pub const fn path(self) -> &'ctx str {
+        self.path
+    }
+
+    pub const fn is_root(self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// The enclosing namespace, or `None` for the root
+    pub fn parent(self) -> Option<Self> {
+        if self.is_root() {
+            return None;
+        }
+
+        Some(Self::split(self.path).0)
+    }
+
+    /// Split a path into the namespace it's in and the last segment
+    pub fn split(path: &'ctx str) -> (Self, &'ctx str) {
+        match path.rsplit_once("::") {
+            Some((namespace, name)) => (Self::new(namespace), name),
+            None => (Self::ROOT, path),
+        }
+    }
+
+    /// The path of `name` within this namespace
+    pub fn join(self, name: &str) -> String {
+        if self.is_root() {
+            name.to_owned()
+        } else {
+            format!("{}::{name}", self.path)
+        }
+    }
+}
+
+impl<'ctx> TypeContext<'ctx> {
+    /// The namespace at `path`, if a named type is declared in it or in a namespace
+    /// inside it
+    ///
+    /// This only looks at existing namespaces, so it never interns `path`.
+    pub fn get_namespace(self, path: &str) -> Option<Namespace<'ctx>> {
+        if path.is_empty() {
+            return Some(Namespace::ROOT);
+        }
+
+        let namespaces = unsafe { &*self.0.as_ref().namespaces.get() };
+        namespaces.keys().find_map(|namespace| {
+            // borrow the path from a namespace that is `path` or inside it
+            let rest = namespace.path.strip_prefix(path)?;
+            (rest.is_empty() || rest.starts_with("::"))
+                .then(|| Namespace::new(&namespace.path[..path.len()]))
+        })
+    }
+
+    /// Every named type declared directly in `namespace`, in the order they were created
+    pub fn iter_namespace(
+        self,
+        namespace: Namespace<'ctx>,
+    ) -> impl Iterator<Item = types::Type<'ctx>> {
+        let namespaces = unsafe { &*self.0.as_ref().namespaces.get() };
+        let members = namespaces.get(&namespace).cloned().unwrap_or_default();
+        members.into_iter()
+    }
+
+    /// The namespaces directly inside `namespace` that contain named types, sorted by
+    /// path
+    pub fn iter_child_namespaces(
+        self,
+        namespace: Namespace<'ctx>,
+    ) -> impl Iterator<Item = Namespace<'ctx>> {
+        let namespaces = unsafe { &*self.0.as_ref().namespaces.get() };
+        let mut children = BTreeSet::new();
+
+        for &descendant in namespaces.keys() {
+            // namespaces only exist while they have members, so walk up from each one to
+            // find the child of `namespace` it is in
+            let mut current = descendant;
+            while let Some(parent) = current.parent() {
+                if parent == namespace {
+                    children.insert(current);
+                    break;
+                }
+                current = parent;
+            }
+        }
+
+        children.into_iter()
+    }
+}
+
+#[test]
+fn test_namespaces() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let int = ctx.int_ty(32);
+        let field = |ty| types::AggregateField::new(ctx.symbol("value"), ty);
+
+        let root_node = ctx.create_aggregate("Node", [field(int)]);
+        let a_node = ctx.create_aggregate("crate::a::Node", [field(int)]);
+        let b_node = ctx.create_aggregate("crate::b::Node", [field(int)]);
+        let handle = ctx.create_opaque("crate::a::inner::Handle");
+
+        // the root namespace works as before
+        assert_eq!(ctx.get_aggregate("Node"), Some(root_node));
+        assert_eq!(ctx.get_aggregate("crate::a::Node"), Some(a_node));
+        assert_ne!(a_node, b_node);
+
+        let a = ctx.namespace("crate::a").unwrap();
+        let inner = ctx.namespace("crate::a::inner").unwrap();
+        let krate = ctx.namespace("crate").unwrap();
+        assert_eq!(inner.parent(), Some(a));
+        assert_eq!(ctx.resolve(inner, "Node"), Some(a_node));
+        assert_eq!(ctx.resolve(inner, "Handle"), Some(handle));
+        assert_eq!(ctx.resolve(a, "inner::Handle"), Some(handle));
+        assert_eq!(ctx.resolve(krate, "Node"), Some(root_node));
+        assert_eq!(ctx.resolve(a, "Missing"), None);
+
+        assert!(ctx.type_ctx().iter_namespace(a).eq([a_node]));
+        assert!(ctx
+            .type_ctx()
+            .iter_child_namespaces(krate)
+            .map(Namespace::path)
+            .eq(["crate::a", "crate::b"]));
+        assert!(ctx
+            .type_ctx()
+            .iter_child_namespaces(Namespace::ROOT)
+            .map(Namespace::path)
+            .eq(["crate"]));
+
+        // looking up a namespace doesn't intern its path
+        assert_eq!(ctx.namespace("crate::a::in"), None);
+        assert_eq!(ctx.namespace("crate::missing"), None);
+        assert!(ctx.get_symbol("crate::missing").is_none());
+        assert_eq!(ctx.namespace(""), Some(Namespace::ROOT));
+    });
+}
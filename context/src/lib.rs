@@ -1,18 +1,86 @@
+pub mod abi;
+
+mod c_type;
+
 mod ctx;
 
 mod ptr;
 
-pub mod types;
+mod symbol;
 
-mod utils;
+mod target;
 
-pub use ctx::{AllocContext, Context, ContextId, TypeContext};
+pub mod types;
+
+pub use c_type::{CDataModel, CType};
+pub use ctx::{
+    AllocContext, CacheStats, Checkpoint, Context, ContextId, ContextStats, Namespace,
+    OwnedContext, TypeContext, LARGEST_TYPES,
+};
+pub use symbol::Symbol;
+pub use target::{TargetError, TargetSpecBuilder, MAX_POINTER_SIZE_BYTES};
 
+/// The properties of the target that affect type layout
+///
+/// Use [`TargetSpec::builder`] to check the spec as it is built, otherwise it is
+/// checked when a context is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TargetSpec {
     pub pointer_size_bytes: u8,
     pub pointer_align_log2: u8,
     pub pointer_diff_size_bytes: u8,
     pub pointer_diff_align_log2: u8,
+    pub code_pointer_size_bytes: u8,
+    pub code_pointer_align_log2: u8,
+    pub function_pointer: FunctionPointerRepr,
+    pub int_layout: IntLayoutRule,
+    pub c_data_model: CDataModel,
+    /// The alignment of x87 extended precision floats, which are padded to a multiple
+    /// of it: 4 bytes on i386, and 16 bytes on x86-64
+    pub x87_float_align_log2: u8,
+}
+
+/// How a function pointer is represented on the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionPointerRepr {
+    /// A function pointer is a code pointer to the entry point (x86, AArch64, AVR)
+    Address,
+    /// A function pointer is a data pointer to a descriptor, which holds the entry
+    /// point and the data the function needs (PowerPC64 ELFv1, IA-64)
+    Descriptor,
+    /// A function pointer is the descriptor itself, stored inline as a code pointer to
+    /// the entry point followed by `words - 1` data pointer sized words
+    InlineDescriptor { words: u8 },
+}
+
+/// How the size and alignment of an integer type is derived from its width
+///
+/// Every rule gives an `iN` a bit size of `N`, they only differ in padding and alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntLayoutRule {
+    /// LLVM's layout of `iN`: the width is rounded up to the next of 8, 16, 32, 64 or
+    /// 128 bits, and aligned like the integer of that size in the target's datalayout.
+    /// `align_log2` holds those alignments, and wider integers are aligned like `i128`.
+    Legacy { align_log2: [u8; 5] },
+    /// The x86-64 psABI rules for `_BitInt(N)`: widths up to 64 bits use the smallest
+    /// of 1, 2, 4 or 8 bytes that fits, wider integers are a sequence of 8 byte chunks
+    /// aligned to 8
+    ///
+    /// This only models `_BitInt`. `__int128` is aligned to 16 bytes on x86-64, while
+    /// `_BitInt(128)` is aligned to 8.
+    X86_64BitInt,
+    /// The AAPCS64 rules for `_BitInt(N)`: widths up to 128 bits use the smallest of
+    /// 1, 2, 4, 8 or 16 bytes that fits, wider integers are a sequence of 16 byte
+    /// chunks aligned to 16
+    Aarch64BitInt,
+}
+
+impl IntLayoutRule {
+    /// [`IntLayoutRule::Legacy`] with every integer up to `i128` aligned to its size, as
+    /// in the datalayouts of x86-64 and AArch64
+    pub const LEGACY: Self = Self::Legacy {
+        align_log2: [0, 1, 2, 3, 4],
+    };
 }
 
 #[cfg(test)]
@@ -21,4 +89,10 @@ const TEST_TARGET_SPEC: TargetSpec = TargetSpec {
     pointer_align_log2: 3,
     pointer_diff_size_bytes: 8,
     pointer_diff_align_log2: 3,
+    code_pointer_size_bytes: 8,
+    code_pointer_align_log2: 3,
+    function_pointer: FunctionPointerRepr::Address,
+    int_layout: IntLayoutRule::LEGACY,
+    c_data_model: CDataModel::LP64,
+    x87_float_align_log2: 4,
 };
@@ -0,0 +1,128 @@
+use std::{
+    cell::UnsafeCell,
+    hash::{BuildHasher, BuildHasherDefault, Hash},
+};
+
+use crate::{ctx::AllocContext, ptr::ContextPtr};
+
+/// A string interned in a context
+///
+/// Symbols from the same context with the same contents are the same allocation, so
+/// they compare and hash by pointer. The string is freed along with the context.
+pub struct Symbol<'ctx>(ContextPtr<'ctx, str>);
+
+#[derive(Default)]
+pub(crate) struct SymbolTable<'ctx> {
+    // each symbol is stored with the order it was interned in, so symbols interned
+    // after a checkpoint can be removed
+    symbols: UnsafeCell<hashbrown::HashTable<(Symbol<'ctx>, usize)>>,
+}
+
+type SymbolHasher = BuildHasherDefault<rustc_hash::FxHasher>;
+
+impl Copy for Symbol<'_> {}
+impl Clone for Symbol<'_> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl Eq for Symbol<'_> {}
+impl PartialEq for Symbol<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Hash for Symbol<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+// ordered by contents, which is consistent with Eq because symbols are unique
+impl PartialOrd for Symbol<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol<'_> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl<'ctx> Symbol<'ctx> {
+    pub const fn as_str(self) -> &'ctx str {
+        self.0.as_ref()
+    }
+}
+
+impl core::ops::Deref for Symbol<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl core::fmt::Debug for Symbol<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl core::fmt::Display for Symbol<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl<'ctx> SymbolTable<'ctx> {
+    pub fn get(&self, name: &str) -> Option<Symbol<'ctx>> {
+        let symbols = unsafe { &*self.symbols.get() };
+        let hash = SymbolHasher::default().hash_one(name);
+        symbols
+            .find(hash, |(sym, _)| sym.as_str() == name)
+            .map(|&(sym, _)| sym)
+    }
+
+    pub fn len(&self) -> usize {
+        unsafe { (*self.symbols.get()).len() }
+    }
+
+    /// Remove every symbol interned after the first `len`
+    pub fn truncate(&mut self, len: usize) {
+        self.symbols.get_mut().retain(|&mut (_, order)| order < len);
+    }
+
+    pub fn intern(&self, alloc: AllocContext<'ctx>, name: &str) -> Symbol<'ctx> {
+        if let Some(sym) = self.get(name) {
+            return sym;
+        }
+
+        let sym = Symbol(ContextPtr::from_ref(alloc.id(), alloc.alloc_str(name)));
+        let symbols = unsafe { &mut *self.symbols.get() };
+        let hash = SymbolHasher::default().hash_one(name);
+        let order = symbols.len();
+        symbols.insert_unique(hash, (sym, order), |(sym, _)| {
+            SymbolHasher::default().hash_one(sym.as_str())
+        });
+        sym
+    }
+}
+
+#[test]
+fn test_intern() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        assert_eq!(ctx.get_symbol("point"), None);
+
+        let a = ctx.symbol("point");
+        let b = ctx.symbol(&String::from("point"));
+        assert_eq!(a, b);
+        assert_eq!(ctx.get_symbol("point"), Some(a));
+        assert_eq!(a.as_str(), "point");
+        assert!(ctx.symbol("line") < a);
+    });
+}
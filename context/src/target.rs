@@ -0,0 +1,359 @@
+use crate::{CDataModel, FunctionPointerRepr, IntLayoutRule, TargetSpec};
+
+/// The widest pointer a target may have, in bytes
+pub const MAX_POINTER_SIZE_BYTES: u8 = 32;
+
+/// The largest alignment of an integer or float with a target-dependent alignment, as a
+/// power of two
+const MAX_SCALAR_ALIGN_LOG2: u8 = 4;
+
+/// Builds a [`TargetSpec`], checking that it describes a target the context can handle
+///
+/// Only the data pointer is required. Pointer differences and code pointers default to
+/// the same size and alignment as data pointers, function pointers default to
+/// [`FunctionPointerRepr::Address`], and integers default to [`IntLayoutRule::LEGACY`].
+/// The C data model defaults to [`CDataModel::ILP32`] for 4-byte pointers and
+/// [`CDataModel::LP64`] for 8-byte pointers, and must be given for any other size. x87
+/// floats are aligned to 4 bytes with 4-byte pointers, as on i386, and to 16 bytes
+/// otherwise.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TargetSpecBuilder {
+    pointer: Option<(u8, u8)>,
+    pointer_diff: Option<(u8, u8)>,
+    code_pointer: Option<(u8, u8)>,
+    function_pointer: Option<FunctionPointerRepr>,
+    int_layout: Option<IntLayoutRule>,
+    c_data_model: Option<CDataModel>,
+    x87_float_align_log2: Option<u8>,
+}
+
+/// Why a [`TargetSpec`] was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetError {
+    /// The size of data pointers wasn't given
+    MissingPointer,
+    /// The C data model wasn't given, and there is no default for the pointer size
+    MissingCDataModel { pointer_size_bytes: u8 },
+    /// `field` is a pointer size the context doesn't handle
+    UnsupportedSize { field: &'static str, bytes: u8 },
+    /// The size given for `field` isn't a multiple of its alignment
+    MisalignedSize {
+        field: &'static str,
+        bytes: u8,
+        align_log2: u8,
+    },
+    /// A function descriptor has no slot for the entry point
+    EmptyDescriptor,
+    /// The C integer types aren't ordered by size, or one of them has no bits
+    InvalidCDataModel,
+    /// `field` is aligned to more than 16 bytes
+    UnsupportedAlignment { field: &'static str, align_log2: u8 },
+}
+
+impl core::fmt::Display for TargetError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::MissingPointer => write!(f, "the pointer size was not set"),
+            Self::MissingCDataModel { pointer_size_bytes } => write!(
+                f,
+                "the C data model was not set, and there is no default for \
+                 {pointer_size_bytes} byte pointers"
+            ),
+            Self::UnsupportedSize { field, bytes } => write!(
+                f,
+                "{field} of {bytes} bytes is not supported, it must be a power of two \
+                 no larger than {MAX_POINTER_SIZE_BYTES}"
+            ),
+            Self::MisalignedSize {
+                field,
+                bytes,
+                align_log2,
+            } => match 1u64.checked_shl(align_log2.into()) {
+                Some(align) => write!(
+                    f,
+                    "{field} of {bytes} bytes is not a multiple of its alignment {align}"
+                ),
+                None => write!(f, "{field} has an alignment of 2^{align_log2} bytes"),
+            },
+            Self::EmptyDescriptor => write!(f, "function descriptors must have a word"),
+            Self::InvalidCDataModel => write!(
+                f,
+                "the C data model must have 0 < short <= int <= long <= long long bits, \
+                 and a non-zero wchar_t"
+            ),
+            Self::UnsupportedAlignment { field, align_log2 } => write!(
+                f,
+                "{field} has an alignment of 2^{align_log2} bytes, which is more than \
+                 2^{MAX_SCALAR_ALIGN_LOG2}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TargetError {}
+
+impl TargetSpec {
+    pub fn builder() -> TargetSpecBuilder {
+        TargetSpecBuilder::default()
+    }
+
+    /// The size in bytes of the largest object, which is `isize::MAX` for the pointer width
+    ///
+    /// This is capped at `u64::MAX / 8` so the size of any object in bits fits in a
+    /// `u64`.
+    pub fn max_object_size(&self) -> u64 {
+        let bits = u32::from(self.pointer_size_bytes) * 8 - 1;
+        let max = 1u64.checked_shl(bits).unwrap_or(0).wrapping_sub(1);
+        max.min(u64::MAX / 8)
+    }
+
+    /// Check that the context can handle this target
+    pub fn validate(&self) -> Result<(), TargetError> {
+        check_pointer("pointer", self.pointer_size_bytes, self.pointer_align_log2)?;
+        check_pointer(
+            "pointer difference",
+            self.pointer_diff_size_bytes,
+            self.pointer_diff_align_log2,
+        )?;
+
+        // code pointers are never used as integers, so any size works
+        check_aligned(
+            "code pointer",
+            self.code_pointer_size_bytes,
+            self.code_pointer_align_log2,
+        )?;
+        if self.code_pointer_size_bytes == 0 {
+            return Err(TargetError::UnsupportedSize {
+                field: "code pointer",
+                bytes: 0,
+            });
+        }
+
+        match self.function_pointer {
+            FunctionPointerRepr::Address | FunctionPointerRepr::Descriptor => (),
+            FunctionPointerRepr::InlineDescriptor { words } => {
+                if words == 0 {
+                    return Err(TargetError::EmptyDescriptor);
+                }
+            }
+        }
+
+        let model = &self.c_data_model;
+        if model.short_bits == 0
+            || model.short_bits > model.int_bits
+            || model.int_bits > model.long_bits
+            || model.long_bits > model.long_long_bits
+            || model.wchar_bits == 0
+        {
+            return Err(TargetError::InvalidCDataModel);
+        }
+
+        if let IntLayoutRule::Legacy { align_log2 } = self.int_layout {
+            if let Some(&align_log2) = align_log2.iter().find(|&&a| a > MAX_SCALAR_ALIGN_LOG2) {
+                return Err(TargetError::UnsupportedAlignment {
+                    field: "integer",
+                    align_log2,
+                });
+            }
+        }
+
+        if self.x87_float_align_log2 > MAX_SCALAR_ALIGN_LOG2 {
+            return Err(TargetError::UnsupportedAlignment {
+                field: "x87 float",
+                align_log2: self.x87_float_align_log2,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn check_pointer(field: &'static str, bytes: u8, align_log2: u8) -> Result<(), TargetError> {
+    if !bytes.is_power_of_two() || bytes > MAX_POINTER_SIZE_BYTES {
+        return Err(TargetError::UnsupportedSize { field, bytes });
+    }
+
+    check_aligned(field, bytes, align_log2)
+}
+
+fn check_aligned(field: &'static str, bytes: u8, align_log2: u8) -> Result<(), TargetError> {
+    match 1u8.checked_shl(align_log2.into()) {
+        Some(align) if bytes % align == 0 => Ok(()),
+        _ => Err(TargetError::MisalignedSize {
+            field,
+            bytes,
+            align_log2,
+        }),
+    }
+}
+
+impl TargetSpecBuilder {
+    pub fn pointer(mut self, size_bytes: u8, align_log2: u8) -> Self {
+        self.pointer = Some((size_bytes, align_log2));
+        self
+    }
+
+    pub fn pointer_diff(mut self, size_bytes: u8, align_log2: u8) -> Self {
+        self.pointer_diff = Some((size_bytes, align_log2));
+        self
+    }
+
+    pub fn code_pointer(mut self, size_bytes: u8, align_log2: u8) -> Self {
+        self.code_pointer = Some((size_bytes, align_log2));
+        self
+    }
+
+    pub fn function_pointer(mut self, repr: FunctionPointerRepr) -> Self {
+        self.function_pointer = Some(repr);
+        self
+    }
+
+    pub fn int_layout(mut self, rule: IntLayoutRule) -> Self {
+        self.int_layout = Some(rule);
+        self
+    }
+
+    pub fn c_data_model(mut self, model: CDataModel) -> Self {
+        self.c_data_model = Some(model);
+        self
+    }
+
+    pub fn x87_float_align(mut self, align_log2: u8) -> Self {
+        self.x87_float_align_log2 = Some(align_log2);
+        self
+    }
+
+    pub fn build(self) -> Result<TargetSpec, TargetError> {
+        let (pointer_size_bytes, pointer_align_log2) =
+            self.pointer.ok_or(TargetError::MissingPointer)?;
+        let (pointer_diff_size_bytes, pointer_diff_align_log2) = self
+            .pointer_diff
+            .unwrap_or((pointer_size_bytes, pointer_align_log2));
+        let (code_pointer_size_bytes, code_pointer_align_log2) = self
+            .code_pointer
+            .unwrap_or((pointer_size_bytes, pointer_align_log2));
+
+        let spec = TargetSpec {
+            pointer_size_bytes,
+            pointer_align_log2,
+            pointer_diff_size_bytes,
+            pointer_diff_align_log2,
+            code_pointer_size_bytes,
+            code_pointer_align_log2,
+            function_pointer: self
+                .function_pointer
+                .unwrap_or(FunctionPointerRepr::Address),
+            int_layout: self.int_layout.unwrap_or(IntLayoutRule::LEGACY),
+            c_data_model: match (self.c_data_model, pointer_size_bytes) {
+                (Some(model), _) => model,
+                (None, 4) => CDataModel::ILP32,
+                (None, 8) => CDataModel::LP64,
+                (None, _) => {
+                    // an unsupported pointer size is the more useful error
+                    check_pointer("pointer", pointer_size_bytes, pointer_align_log2)?;
+                    return Err(TargetError::MissingCDataModel { pointer_size_bytes });
+                }
+            },
+            x87_float_align_log2: self
+                .x87_float_align_log2
+                .unwrap_or(if pointer_size_bytes == 4 { 2 } else { 4 }),
+        };
+
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+impl From<TargetSpec> for TargetSpecBuilder {
+    fn from(spec: TargetSpec) -> Self {
+        Self {
+            pointer: Some((spec.pointer_size_bytes, spec.pointer_align_log2)),
+            pointer_diff: Some((spec.pointer_diff_size_bytes, spec.pointer_diff_align_log2)),
+            code_pointer: Some((spec.code_pointer_size_bytes, spec.code_pointer_align_log2)),
+            function_pointer: Some(spec.function_pointer),
+            int_layout: Some(spec.int_layout),
+            c_data_model: Some(spec.c_data_model),
+            x87_float_align_log2: Some(spec.x87_float_align_log2),
+        }
+    }
+}
+
+#[test]
+fn test_target_builder() {
+    let spec = TargetSpec::builder().pointer(8, 3).build().unwrap();
+    assert_eq!(spec, crate::TEST_TARGET_SPEC);
+    assert_eq!(TargetSpecBuilder::from(spec).build(), Ok(spec));
+
+    let i386 = TargetSpec::builder().pointer(4, 2).build().unwrap();
+    assert_eq!(i386.c_data_model, CDataModel::ILP32);
+    assert_eq!(i386.x87_float_align_log2, 2);
+
+    let avr_model = CDataModel {
+        int_bits: 16,
+        ..CDataModel::ILP32
+    };
+    let avr = TargetSpec::builder()
+        .pointer(2, 0)
+        .code_pointer(3, 0)
+        .c_data_model(avr_model)
+        .build()
+        .unwrap();
+    assert_eq!(avr.pointer_diff_size_bytes, 2);
+    assert_eq!(avr.c_data_model, avr_model);
+    assert_eq!(TargetSpecBuilder::from(avr).build(), Ok(avr));
+    assert_eq!(
+        TargetSpec::builder().pointer(2, 0).build(),
+        Err(TargetError::MissingCDataModel {
+            pointer_size_bytes: 2
+        })
+    );
+
+    assert_eq!(
+        TargetSpec::builder().build(),
+        Err(TargetError::MissingPointer)
+    );
+    assert_eq!(
+        TargetSpec::builder().pointer(6, 1).build(),
+        Err(TargetError::UnsupportedSize {
+            field: "pointer",
+            bytes: 6
+        })
+    );
+    assert_eq!(
+        TargetSpec::builder().pointer(4, 3).build(),
+        Err(TargetError::MisalignedSize {
+            field: "pointer",
+            bytes: 4,
+            align_log2: 3
+        })
+    );
+    assert_eq!(
+        TargetSpec::builder()
+            .pointer(8, 3)
+            .function_pointer(FunctionPointerRepr::InlineDescriptor { words: 0 })
+            .build(),
+        Err(TargetError::EmptyDescriptor)
+    );
+    assert_eq!(
+        TargetSpec::builder()
+            .pointer(8, 3)
+            .c_data_model(CDataModel {
+                long_bits: 16,
+                ..CDataModel::LP64
+            })
+            .build(),
+        Err(TargetError::InvalidCDataModel)
+    );
+    assert_eq!(
+        TargetSpec::builder()
+            .pointer(8, 3)
+            .int_layout(IntLayoutRule::Legacy {
+                align_log2: [0, 1, 2, 3, 5]
+            })
+            .build(),
+        Err(TargetError::UnsupportedAlignment {
+            field: "integer",
+            align_log2: 5
+        })
+    );
+}
@@ -1,21 +1,41 @@
 mod raw;
 
 mod aggregate;
+mod alias;
+mod attributes;
 mod float;
+mod fn_pointer;
 mod func;
 mod int;
+mod map;
+mod opaque;
+mod param;
 mod pointer;
+mod pointer_map;
+mod structural;
 mod unit;
 
 pub type Type<'ctx> = raw::RawType<'ctx>;
-pub use raw::TypeKind;
+pub use raw::{ConcreteLayout, Layout, LayoutError, TypeKind};
 
-pub use aggregate::{AggregateField, AggregateLayoutProvider, AggregateTy};
-pub use float::{FloatKind, FloatTy};
+pub use aggregate::{
+    AggregateField, AggregateLayoutProvider, AggregateRepr, AggregateTy, BitfieldLayout,
+    FieldLayout, LayoutAlgorithm,
+};
+pub use alias::AliasTy;
+pub use attributes::{AttributeFlags, AttributeValue, Attributes, SourceLocation};
+pub use float::{FloatKind, FloatSemantics, FloatTy};
+pub use fn_pointer::FnPointerTy;
 pub use func::{FuncLayoutProvider, FuncTy};
 pub use int::IntTy;
+pub use map::{TypeMap, TypeSet};
+pub use opaque::OpaqueTy;
+pub use param::ParamTy;
 pub use pointer::PointerTy;
+pub use pointer_map::PointerMap;
 pub use unit::UnitTy;
 
+#[cfg(not(doc))]
+pub(crate) use raw::RawType;
 #[cfg(doc)]
 pub use raw::{BasicTypeData, RawType, TypeData};
@@ -2,6 +2,11 @@ use core::mem::offset_of;
 use std::alloc::Layout;
 
 use super::raw::{BasicTypeData, RawType, TypeData, TypeHeader, TypeKind};
+use crate::Symbol;
+
+mod layout;
+
+pub use layout::{BitfieldLayout, FieldLayout, LayoutAlgorithm};
 
 pub type AggregateTy<'ctx> = RawType<'ctx, AggregateData<'ctx>>;
 
@@ -9,14 +14,66 @@ pub type AggregateTy<'ctx> = RawType<'ctx, AggregateData<'ctx>>;
 pub struct AggregateData<'ctx> {
     header: TypeHeader,
     len: usize,
-    pub name: istr::IBytes,
+    pub name: Symbol<'ctx>,
+    pub repr: AggregateRepr,
+    /// The number of type parameters, which is zero unless this is a generic aggregate
+    pub params: u32,
     pub fields: [AggregateField<'ctx>],
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AggregateRepr {
+    pub algorithm: LayoutAlgorithm,
+    /// The minimum alignment of the aggregate, which must be a power of two
+    pub align: Option<u64>,
+    /// Whether fields with explicit offsets may overlap, as in a union
+    pub allow_overlap: bool,
+}
+
 #[derive(Clone, Copy)]
 pub struct AggregateField<'ctx> {
-    pub name: istr::IBytes,
+    pub name: Symbol<'ctx>,
     pub ty: super::Type<'ctx>,
+    /// The width in bits if this field is a bitfield
+    pub bit_width: Option<u16>,
+    /// The byte offset of this field, or of its storage unit if it is a bitfield.
+    /// Fields without an explicit offset are placed after the previous field.
+    pub offset: Option<u64>,
+}
+
+impl<'ctx> AggregateField<'ctx> {
+    pub const fn new(name: Symbol<'ctx>, ty: super::Type<'ctx>) -> Self {
+        Self {
+            name,
+            ty,
+            bit_width: None,
+            offset: None,
+        }
+    }
+
+    /// Create a bitfield, `ty` must be an integer type at least `bit_width` bits wide
+    pub fn bitfield(name: Symbol<'ctx>, ty: super::Type<'ctx>, bit_width: u16) -> Self {
+        let bits = ty.cast::<super::IntTy>().bits().get();
+        assert!(
+            bit_width <= bits,
+            "a bitfield of {bit_width} bits doesn't fit in an i{bits}"
+        );
+
+        Self {
+            name,
+            ty,
+            bit_width: Some(bit_width),
+            offset: None,
+        }
+    }
+
+    /// Place this field at an explicit byte offset
+    pub const fn at_offset(self, offset: u64) -> Self {
+        Self {
+            offset: Some(offset),
+            ..self
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -41,7 +98,12 @@ impl From<init::slice::IterInitError<core::convert::Infallible>> for NotEnoughFi
 }
 
 impl<'ctx> AggregateTy<'ctx> {
-    pub(crate) fn init_data<I>(name: istr::IBytes, iter: I) -> AggregateDataInit<I::IntoIter>
+    pub(crate) fn init_data<I>(
+        name: Symbol<'ctx>,
+        repr: AggregateRepr,
+        params: u32,
+        iter: I,
+    ) -> AggregateDataInit<'ctx, I::IntoIter>
     where
         I: IntoIterator<Item = AggregateField<'ctx>>,
         I::IntoIter: ExactSizeIterator,
@@ -49,23 +111,54 @@ impl<'ctx> AggregateTy<'ctx> {
         let iter = iter.into_iter();
         AggregateDataInit {
             name,
+            repr,
+            params,
             len: iter.len(),
             iter,
         }
     }
 
-    pub fn name(self) -> istr::IBytes {
+    pub fn name(self) -> Symbol<'ctx> {
         self.get().name
     }
 
+    pub fn repr(self) -> AggregateRepr {
+        self.get().repr
+    }
+
+    /// The number of type parameters of a generic aggregate
+    pub fn params(self) -> u32 {
+        self.get().params
+    }
+
+    /// Whether this aggregate must be [instantiated](crate::Context::instantiate) before
+    /// it can be used
+    pub fn is_generic(self) -> bool {
+        self.params() != 0
+    }
+
     pub fn fields(self) -> &'ctx [AggregateField<'ctx>] {
         &self.get().fields
     }
+
+    /// The placement of each field, or `None` if the layout isn't concrete
+    pub fn field_layouts(
+        self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<Option<Vec<FieldLayout>>, super::raw::LayoutError> {
+        let mut fields = Vec::with_capacity(self.fields().len());
+        match self.get().compute_layout(ctx, |field| fields.push(field))? {
+            super::raw::Layout::Concrete(_) => Ok(Some(fields)),
+            _ => Ok(None),
+        }
+    }
 }
 
 impl<'ctx> AggregateData<'ctx> {
     fn init<I>(
-        name: istr::IBytes,
+        name: Symbol<'ctx>,
+        repr: AggregateRepr,
+        params: u32,
         iter: I,
     ) -> impl init::Initializer<Self, Error = NotEnoughFieldsError>
     where
@@ -75,6 +168,8 @@ impl<'ctx> AggregateData<'ctx> {
             init::init_struct! {
                 ptr => Self {
                     name: init::init(name),
+                    repr: init::init(repr),
+                    params: init::init(params),
                     header: init::init_fn(|ptr| ptr.write(TypeHeader::of::<Self>())),
                     fields: init::slice::IterArgs::new(iter.into_iter().map(init::init)),
                     len: fields.len(),
@@ -87,34 +182,11 @@ impl<'ctx> AggregateData<'ctx> {
 unsafe impl<'ctx> BasicTypeData<'ctx> for AggregateData<'ctx> {
     const KIND: TypeKind = TypeKind::Aggregate;
 
-    fn layout(&self, ctx: crate::Context<'ctx>) -> super::raw::Layout {
-        let mut layout =
-            super::raw::Layout::Concrete(super::raw::ConcreteLayout { size: 0, align: 1 });
-
-        for field in &self.fields {
-            let next = match field.ty.layout(ctx) {
-                super::raw::Layout::Concrete(next) => next,
-                super::raw::Layout::RuntimeKnown => {
-                    todo!("runtime layouts aren't supported yet in Aggregates")
-                }
-                super::raw::Layout::Unknown => return super::raw::Layout::Unknown,
-            };
-
-            match layout {
-                super::raw::Layout::Concrete(l) => {
-                    let align = l.align.max(next.align);
-                    let size = l.size.next_multiple_of(next.align) + next.size;
-                    layout =
-                        super::raw::Layout::Concrete(super::raw::ConcreteLayout { size, align });
-                }
-                super::raw::Layout::RuntimeKnown => {
-                    todo!("runtime layouts aren't supported yet in Aggregates")
-                }
-                super::raw::Layout::Unknown => unreachable!(),
-            };
-        }
-
-        layout
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        self.compute_layout(ctx, |_| ())
     }
 }
 
@@ -137,13 +209,15 @@ unsafe impl<'ctx> TypeData<'ctx> for AggregateData<'ctx> {
     }
 }
 
-pub struct AggregateDataInit<I> {
-    name: istr::IBytes,
+pub struct AggregateDataInit<'ctx, I> {
+    name: Symbol<'ctx>,
+    repr: AggregateRepr,
+    params: u32,
     len: usize,
     iter: I,
 }
 
-impl<'ctx, I> init::Initializer<AggregateData<'ctx>> for AggregateDataInit<I>
+impl<'ctx, I> init::Initializer<AggregateData<'ctx>> for AggregateDataInit<'ctx, I>
 where
     I: IntoIterator<Item = AggregateField<'ctx>>,
 {
@@ -153,18 +227,25 @@ where
         self,
         ptr: init::ptr::Uninit<'a, AggregateData<'ctx>>,
     ) -> Result<init::ptr::Init<'a, AggregateData<'ctx>>, Self::Error> {
-        ptr.try_init(AggregateData::init(self.name, self.iter))
+        ptr.try_init(AggregateData::init(
+            self.name,
+            self.repr,
+            self.params,
+            self.iter,
+        ))
     }
 }
 
 unsafe impl<'ctx, I>
-    init::layout_provider::LayoutProvider<AggregateData<'ctx>, AggregateDataInit<I>>
+    init::layout_provider::LayoutProvider<AggregateData<'ctx>, AggregateDataInit<'ctx, I>>
     for AggregateLayoutProvider
 {
-    fn layout_for(args: &AggregateDataInit<I>) -> Option<std::alloc::Layout> {
+    fn layout_for(args: &AggregateDataInit<'ctx, I>) -> Option<std::alloc::Layout> {
         let layout = Layout::new::<TypeHeader>();
         let (layout, _) = layout.extend(Layout::new::<usize>()).ok()?;
-        let (layout, _) = layout.extend(Layout::new::<istr::IBytes>()).ok()?;
+        let (layout, _) = layout.extend(Layout::new::<Symbol<'ctx>>()).ok()?;
+        let (layout, _) = layout.extend(Layout::new::<AggregateRepr>()).ok()?;
+        let (layout, _) = layout.extend(Layout::new::<u32>()).ok()?;
         let (layout, _) = layout
             .extend(Layout::array::<AggregateField>(args.len).ok()?)
             .ok()?;
@@ -173,7 +254,7 @@ unsafe impl<'ctx, I>
 
     unsafe fn cast(
         ptr: std::ptr::NonNull<u8>,
-        args: &AggregateDataInit<I>,
+        args: &AggregateDataInit<'ctx, I>,
     ) -> std::ptr::NonNull<AggregateData<'ctx>> {
         let ptr = std::ptr::NonNull::slice_from_raw_parts(ptr, args.len);
         let ptr = ptr.as_ptr() as *mut AggregateData<'ctx>;
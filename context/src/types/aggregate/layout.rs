@@ -0,0 +1,587 @@
+use super::super::raw::{ConcreteLayout, Layout, LayoutError};
+use super::AggregateData;
+
+/// How fields are allocated within an aggregate, the algorithms only differ for bitfields
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutAlgorithm {
+    /// The SysV/Itanium C ABI rules, as used by GCC and Clang
+    ///
+    /// A bitfield is placed at the next free bit, unless it would straddle a storage
+    /// unit of its declared type, and zero-width bitfields align to their declared type.
+    #[default]
+    Itanium,
+    /// The MSVC rules
+    ///
+    /// Adjacent bitfields share a storage unit only if their declared types have the
+    /// same size and there is enough room left, and zero-width bitfields close the
+    /// current storage unit.
+    Msvc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The byte offset of the field, or of its storage unit if it is a bitfield
+    pub offset: u64,
+    pub bitfield: Option<BitfieldLayout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitfieldLayout {
+    /// The offset in bits from the start of the aggregate
+    pub bit_offset: u64,
+    pub bit_width: u16,
+    /// The size in bytes of the storage unit the bitfield is accessed through
+    pub storage_size: u64,
+}
+
+struct Builder {
+    algorithm: LayoutAlgorithm,
+    /// the bit offset the next field is placed after
+    bits: u64,
+    /// the end of the furthest field placed so far
+    end: u64,
+    align: u64,
+    /// the storage unit bitfields are currently allocated in, only used by the MSVC rules
+    unit: Option<StorageUnit>,
+    /// the target's maximum object size, so offsets in bits always fit in a `u64`
+    max_size: u64,
+}
+
+#[derive(Clone, Copy)]
+struct StorageUnit {
+    offset: u64,
+    size: u64,
+    used_bits: u64,
+}
+
+impl<'ctx> AggregateData<'ctx> {
+    pub(super) fn compute_layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+        mut on_field: impl FnMut(FieldLayout),
+    ) -> Result<Layout, LayoutError> {
+        let mut builder = Builder {
+            algorithm: self.repr.algorithm,
+            bits: 0,
+            end: 0,
+            align: 1,
+            unit: None,
+            max_size: ctx.target().max_object_size(),
+        };
+
+        // the bits occupied by each field, used to detect overlapping fields
+        let mut occupied = Vec::new();
+
+        for (index, field) in self.fields.iter().enumerate() {
+            let next = match field.ty.layout(ctx)? {
+                Layout::Concrete(next) => next.limit(ctx.target())?,
+                Layout::RuntimeKnown => {
+                    todo!("runtime layouts aren't supported yet in Aggregates")
+                }
+                Layout::Unknown => return Ok(Layout::Unknown),
+            };
+
+            if let Some(offset) = field.offset {
+                if offset % next.align != 0 {
+                    return Err(LayoutError::MisalignedField {
+                        field: index,
+                        offset,
+                        align: next.align,
+                    });
+                }
+
+                builder.unit = None;
+                builder.bits = builder.limit(Some(offset))? * 8;
+            }
+
+            let field_layout = match field.bit_width {
+                None => builder.field(next)?,
+                Some(width) => match builder.algorithm {
+                    LayoutAlgorithm::Itanium => builder.itanium_bitfield(next, width)?,
+                    LayoutAlgorithm::Msvc => builder.msvc_bitfield(next, width)?,
+                },
+            };
+
+            builder.end = builder.end.max(builder.bits);
+
+            let (start, width) = match field_layout.bitfield {
+                Some(bitfield) => (bitfield.bit_offset, u64::from(bitfield.bit_width)),
+                None => (field_layout.offset * 8, next.size * 8),
+            };
+
+            if width != 0 {
+                occupied.push((start, start + width, index));
+            }
+
+            on_field(field_layout);
+        }
+
+        if !self.repr.allow_overlap {
+            occupied.sort_unstable();
+
+            let mut furthest = None::<(u64, usize)>;
+            for (start, end, index) in occupied {
+                if let Some((furthest_end, furthest_index)) = furthest {
+                    if start < furthest_end {
+                        return Err(LayoutError::OverlappingFields {
+                            first: furthest_index.min(index),
+                            second: furthest_index.max(index),
+                        });
+                    }
+                }
+
+                if furthest.is_none_or(|(furthest_end, _)| end > furthest_end) {
+                    furthest = Some((end, index));
+                }
+            }
+        }
+
+        let mut align = builder.align;
+
+        if let Some(min_align) = self.repr.align {
+            if !min_align.is_power_of_two() {
+                return Err(LayoutError::InvalidAlignment(min_align));
+            }
+
+            align = align.max(min_align);
+        }
+
+        let size = builder.end.div_ceil(8).checked_next_multiple_of(align);
+        let layout = ConcreteLayout::new(builder.limit(size)?, align);
+        Ok(Layout::Concrete(layout.limit(ctx.target())?))
+    }
+}
+
+impl Builder {
+    /// Check that a size or offset in bytes didn't overflow or exceed the maximum size
+    fn limit(&self, bytes: Option<u64>) -> Result<u64, LayoutError> {
+        bytes
+            .filter(|&bytes| bytes <= self.max_size)
+            .ok_or(LayoutError::TooLarge {
+                max_size: self.max_size,
+            })
+    }
+
+    /// Check that an offset in bits didn't overflow or exceed the maximum size
+    fn limit_bits(&self, bits: Option<u64>) -> Result<u64, LayoutError> {
+        bits.filter(|&bits| bits <= self.max_size * 8)
+            .ok_or(LayoutError::TooLarge {
+                max_size: self.max_size,
+            })
+    }
+
+    /// The offset of the first byte after the last bit used that is aligned to `align`
+    fn next_offset(&self, align: u64) -> Result<u64, LayoutError> {
+        self.limit(self.bits.div_ceil(8).checked_next_multiple_of(align))
+    }
+
+    fn field(&mut self, layout: ConcreteLayout) -> Result<FieldLayout, LayoutError> {
+        self.unit = None;
+
+        let offset = self.next_offset(layout.align)?;
+        self.bits = self.limit(offset.checked_add(layout.size))? * 8;
+        self.align = self.align.max(layout.align);
+
+        Ok(FieldLayout {
+            offset,
+            bitfield: None,
+        })
+    }
+
+    fn itanium_bitfield(
+        &mut self,
+        layout: ConcreteLayout,
+        bit_width: u16,
+    ) -> Result<FieldLayout, LayoutError> {
+        let width = u64::from(bit_width);
+        let unit_align = layout.align * 8;
+
+        let bit_offset = if width == 0 || self.bits % unit_align + width > layout.size * 8 {
+            self.limit_bits(self.bits.checked_next_multiple_of(unit_align))?
+        } else {
+            self.bits
+        };
+
+        self.bits = self.limit_bits(bit_offset.checked_add(width))?;
+
+        // unnamed zero-width bitfields don't affect the alignment of the aggregate
+        if width != 0 {
+            self.align = self.align.max(layout.align);
+        }
+
+        Ok(FieldLayout {
+            offset: bit_offset / unit_align * layout.align,
+            bitfield: Some(BitfieldLayout {
+                bit_offset,
+                bit_width,
+                storage_size: if width == 0 { 0 } else { layout.size },
+            }),
+        })
+    }
+
+    fn msvc_bitfield(
+        &mut self,
+        layout: ConcreteLayout,
+        bit_width: u16,
+    ) -> Result<FieldLayout, LayoutError> {
+        let width = u64::from(bit_width);
+
+        if width == 0 {
+            self.unit = None;
+            let offset = self.next_offset(1)?;
+
+            return Ok(FieldLayout {
+                offset,
+                bitfield: Some(BitfieldLayout {
+                    bit_offset: offset * 8,
+                    bit_width,
+                    storage_size: 0,
+                }),
+            });
+        }
+
+        let unit = match self.unit {
+            Some(unit) if unit.size == layout.size && unit.used_bits + width <= unit.size * 8 => {
+                unit
+            }
+            _ => {
+                let offset = self.next_offset(layout.align)?;
+                self.bits = self.limit(offset.checked_add(layout.size))? * 8;
+                self.align = self.align.max(layout.align);
+
+                StorageUnit {
+                    offset,
+                    size: layout.size,
+                    used_bits: 0,
+                }
+            }
+        };
+
+        self.unit = Some(StorageUnit {
+            used_bits: unit.used_bits + width,
+            ..unit
+        });
+
+        Ok(FieldLayout {
+            offset: unit.offset,
+            bitfield: Some(BitfieldLayout {
+                bit_offset: unit.offset * 8 + unit.used_bits,
+                bit_width,
+                storage_size: unit.size,
+            }),
+        })
+    }
+}
+
+#[test]
+fn test_bitfields() {
+    use crate::types::{AggregateField, Type};
+
+    fn aggregate<'ctx>(
+        ctx: crate::Context<'ctx>,
+        name: &str,
+        algorithm: LayoutAlgorithm,
+        fields: &[(Type<'ctx>, Option<u16>)],
+    ) -> super::AggregateTy<'ctx> {
+        let fields = fields.iter().map(|&(ty, width)| match width {
+            None => AggregateField::new(ctx.symbol("field"), ty),
+            Some(width) => AggregateField::bitfield(ctx.symbol("field"), ty, width),
+        });
+
+        let repr = super::AggregateRepr {
+            algorithm,
+            ..Default::default()
+        };
+        ctx.create_aggregate_with(name, repr, fields)
+            .cast::<super::AggregateTy>()
+    }
+
+    fn size_align<'ctx>(ctx: crate::Context<'ctx>, ty: super::AggregateTy<'ctx>) -> (u64, u64) {
+        match ty.erase().layout(ctx).unwrap() {
+            Layout::Concrete(layout) => (layout.size, layout.align),
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+    }
+
+    fn bits(fields: &[FieldLayout]) -> Vec<(u64, u64)> {
+        fields
+            .iter()
+            .map(|field| match field.bitfield {
+                Some(bitfield) => (bitfield.bit_offset, field.offset),
+                None => (field.offset * 8, field.offset),
+            })
+            .collect()
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let char = ctx.int_ty(8);
+        let short = ctx.int_ty(16);
+        let int = ctx.int_ty(32);
+
+        for (algorithm, prefix) in [
+            (LayoutAlgorithm::Itanium, "itanium"),
+            (LayoutAlgorithm::Msvc, "msvc"),
+        ] {
+            let itanium = algorithm == LayoutAlgorithm::Itanium;
+
+            // struct { unsigned a : 3; unsigned b : 5; unsigned c : 25; char d; }
+            let ty = aggregate(
+                ctx,
+                &format!("{prefix}-straddle"),
+                algorithm,
+                &[
+                    (int, Some(3)),
+                    (int, Some(5)),
+                    (int, Some(25)),
+                    (char, None),
+                ],
+            );
+            let fields = ty.field_layouts(ctx).unwrap().unwrap();
+            assert_eq!(bits(&fields), [(0, 0), (3, 0), (32, 4), (64, 8)]);
+            assert_eq!(size_align(ctx, ty), (12, 4));
+            assert_eq!(fields[2].bitfield.unwrap().storage_size, 4);
+
+            // struct { char a; int b : 4; }
+            let ty = aggregate(
+                ctx,
+                &format!("{prefix}-after-char"),
+                algorithm,
+                &[(char, None), (int, Some(4))],
+            );
+            let fields = ty.field_layouts(ctx).unwrap().unwrap();
+            if itanium {
+                assert_eq!(bits(&fields), [(0, 0), (8, 0)]);
+                assert_eq!(size_align(ctx, ty), (4, 4));
+            } else {
+                assert_eq!(bits(&fields), [(0, 0), (32, 4)]);
+                assert_eq!(size_align(ctx, ty), (8, 4));
+            }
+
+            // struct { char a : 4; int b : 4; }
+            let ty = aggregate(
+                ctx,
+                &format!("{prefix}-mixed"),
+                algorithm,
+                &[(char, Some(4)), (int, Some(4))],
+            );
+            let fields = ty.field_layouts(ctx).unwrap().unwrap();
+            if itanium {
+                assert_eq!(bits(&fields), [(0, 0), (4, 0)]);
+                assert_eq!(size_align(ctx, ty), (4, 4));
+            } else {
+                assert_eq!(bits(&fields), [(0, 0), (32, 4)]);
+                assert_eq!(size_align(ctx, ty), (8, 4));
+            }
+
+            // struct { int a : 1; int : 0; int b : 1; }
+            let ty = aggregate(
+                ctx,
+                &format!("{prefix}-zero-width"),
+                algorithm,
+                &[(int, Some(1)), (int, Some(0)), (int, Some(1))],
+            );
+            let fields = ty.field_layouts(ctx).unwrap().unwrap();
+            assert_eq!(fields[2].bitfield.unwrap().bit_offset, 32);
+            assert_eq!(size_align(ctx, ty), (8, 4));
+
+            // struct { short a : 9; short b : 9; }
+            let ty = aggregate(
+                ctx,
+                &format!("{prefix}-short"),
+                algorithm,
+                &[(short, Some(9)), (short, Some(9))],
+            );
+            let fields = ty.field_layouts(ctx).unwrap().unwrap();
+            assert_eq!(bits(&fields), [(0, 0), (16, 2)]);
+            assert_eq!(size_align(ctx, ty), (4, 2));
+        }
+    })
+}
+
+#[test]
+fn test_explicit_layout() {
+    use crate::types::{AggregateField, AggregateRepr, Type};
+
+    fn layout<'ctx>(
+        ctx: crate::Context<'ctx>,
+        name: &str,
+        repr: AggregateRepr,
+        fields: &[AggregateField<'ctx>],
+    ) -> Result<(u64, u64), LayoutError> {
+        let ty: Type = ctx.create_aggregate_with(name, repr, fields.iter().copied());
+        match ty.layout(ctx)? {
+            Layout::Concrete(layout) => Ok((layout.size, layout.align)),
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let name = || ctx.symbol("field");
+        let char = ctx.int_ty(8);
+        let short = ctx.int_ty(16);
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+
+        // struct alignas(16) { char c; }
+        let aligned = AggregateRepr {
+            align: Some(16),
+            ..Default::default()
+        };
+        let fields = [AggregateField::new(name(), char)];
+        assert_eq!(layout(ctx, "aligned", aligned, &fields), Ok((16, 16)));
+
+        let bad_align = AggregateRepr {
+            align: Some(12),
+            ..Default::default()
+        };
+        assert_eq!(
+            layout(ctx, "bad-align", bad_align, &fields),
+            Err(LayoutError::InvalidAlignment(12))
+        );
+
+        // a device register map, with a 3-bit field in the register at 0xC
+        let fields = [
+            AggregateField::new(name(), int).at_offset(0x0),
+            AggregateField::new(name(), int).at_offset(0x8),
+            AggregateField::bitfield(name(), int, 3).at_offset(0xC),
+            AggregateField::new(name(), short).at_offset(0x10),
+        ];
+        assert_eq!(
+            layout(ctx, "registers", AggregateRepr::default(), &fields),
+            Ok((20, 4))
+        );
+        let registers = ctx.get_aggregate("registers").unwrap();
+        let registers = registers.cast::<super::AggregateTy>();
+        let field_layouts = registers.field_layouts(ctx).unwrap().unwrap();
+        assert_eq!(field_layouts[2].bitfield.unwrap().bit_offset, 0xC * 8);
+        assert_eq!(field_layouts[3].offset, 0x10);
+
+        let fields = [AggregateField::new(name(), int).at_offset(2)];
+        assert_eq!(
+            layout(ctx, "misaligned", AggregateRepr::default(), &fields),
+            Err(LayoutError::MisalignedField {
+                field: 0,
+                offset: 2,
+                align: 4
+            })
+        );
+
+        // [StructLayout(LayoutKind.Explicit)] struct { [FieldOffset(0)] long a; [FieldOffset(4)] int b; }
+        let fields = [
+            AggregateField::new(name(), long).at_offset(0),
+            AggregateField::new(name(), int).at_offset(4),
+        ];
+        assert_eq!(
+            layout(ctx, "overlapping", AggregateRepr::default(), &fields),
+            Err(LayoutError::OverlappingFields {
+                first: 0,
+                second: 1
+            })
+        );
+
+        let union = AggregateRepr {
+            allow_overlap: true,
+            ..Default::default()
+        };
+        assert_eq!(layout(ctx, "union", union, &fields), Ok((8, 8)));
+    })
+}
+
+#[test]
+fn test_tail_padding() {
+    use crate::types::{AggregateField, Type};
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let name = || ctx.symbol("field");
+        let char = ctx.int_ty(8);
+        let int = ctx.int_ty(32);
+
+        // struct inner { int i; char c; } is padded to a multiple of its alignment, so
+        // an array of it keeps every element aligned
+        let inner: Type = ctx.create_aggregate(
+            "inner",
+            [
+                AggregateField::new(name(), int),
+                AggregateField::new(name(), char),
+            ],
+        );
+        let Layout::Concrete(layout) = inner.layout(ctx).unwrap() else {
+            panic!("expected a concrete layout");
+        };
+        assert_eq!((layout.size, layout.align), (8, 4));
+
+        // struct outer { struct inner i; char c; } places `c` after the padding
+        let outer: Type = ctx.create_aggregate(
+            "outer",
+            [
+                AggregateField::new(name(), inner),
+                AggregateField::new(name(), char),
+            ],
+        );
+        let field_layouts = outer
+            .cast::<super::AggregateTy>()
+            .field_layouts(ctx)
+            .unwrap()
+            .unwrap();
+        assert_eq!(field_layouts[1].offset, 8);
+        let Layout::Concrete(layout) = outer.layout(ctx).unwrap() else {
+            panic!("expected a concrete layout");
+        };
+        assert_eq!((layout.size, layout.align), (12, 4));
+    })
+}
+
+#[test]
+fn test_too_large() {
+    use crate::types::{AggregateField, AggregateRepr, Type};
+
+    fn layout<'ctx>(
+        ctx: crate::Context<'ctx>,
+        name: &str,
+        fields: &[AggregateField<'ctx>],
+    ) -> Result<Layout, LayoutError> {
+        let ty: Type =
+            ctx.create_aggregate_with(name, AggregateRepr::default(), fields.iter().copied());
+        ty.layout(ctx)
+    }
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let name = || ctx.symbol("field");
+        let char = ctx.int_ty(8);
+        let max_size = ctx.target().max_object_size();
+        let too_large = Some(LayoutError::TooLarge { max_size });
+
+        let fields = [AggregateField::new(name(), char).at_offset(u64::MAX - 7)];
+        assert_eq!(layout(ctx, "wraps", &fields).err(), too_large);
+
+        // each half fits, but together they don't
+        let fields = [AggregateField::new(name(), char).at_offset(max_size / 2 + 1)];
+        assert!(layout(ctx, "half", &fields).is_ok());
+        let half = ctx.aggregate("half");
+        let fields = [
+            AggregateField::new(name(), half),
+            AggregateField::new(name(), half),
+        ];
+        assert_eq!(layout(ctx, "double", &fields).err(), too_large);
+
+        let fields = [AggregateField::bitfield(name(), char, 3).at_offset(max_size)];
+        assert_eq!(layout(ctx, "bitfield", &fields).err(), too_large);
+    });
+
+    let avr = crate::TargetSpec::builder()
+        .pointer(2, 0)
+        .c_data_model(crate::CDataModel {
+            int_bits: 16,
+            ..crate::CDataModel::ILP32
+        })
+        .build()
+        .unwrap();
+    assert_eq!(avr.max_object_size(), 0x7fff);
+    crate::Context::with(avr, |ctx| {
+        let fields = [AggregateField::new(ctx.symbol("field"), ctx.int_ty(8)).at_offset(0x7fff)];
+        assert_eq!(
+            layout(ctx, "past-the-end", &fields).err(),
+            Some(LayoutError::TooLarge { max_size: 0x7fff })
+        );
+    });
+}
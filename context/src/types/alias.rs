@@ -0,0 +1,105 @@
+use crate::Symbol;
+
+use super::raw::{BasicTypeData, RawType, TypeHeader, TypeKind};
+
+/// Another name for an existing type, like a C `typedef`
+///
+/// An alias has the same layout as the type it names, but is a separate type with its
+/// own identity, so it can be printed under its own name. This means `==`, a
+/// [`TypeMap`](super::TypeMap), and the interning of function and pointer types all
+/// tell an alias apart from the type it names: a function taking `size_t` is a
+/// different [`FuncTy`](super::FuncTy) from one taking `uintptr_t`. Use
+/// [`Type::strip_aliases`](super::Type) to get the underlying type, and
+/// [`Type::structurally_equal`](super::Type) or
+/// [`Context::canonicalize`](crate::Context::canonicalize) to compare through aliases.
+pub type AliasTy<'ctx> = RawType<'ctx, AliasData<'ctx>>;
+
+#[repr(C)]
+pub struct AliasData<'ctx> {
+    header: TypeHeader,
+    pub name: Symbol<'ctx>,
+    pub aliased: super::Type<'ctx>,
+}
+
+impl<'ctx> init::Ctor<(Symbol<'ctx>, super::Type<'ctx>)> for AliasData<'ctx> {
+    type Error = core::convert::Infallible;
+
+    fn try_init(
+        ptr: init::ptr::Uninit<Self>,
+        (name, aliased): (Symbol<'ctx>, super::Type<'ctx>),
+    ) -> Result<init::ptr::Init<Self>, Self::Error> {
+        Ok(ptr.write(Self {
+            header: TypeHeader::of::<Self>(),
+            name,
+            aliased,
+        }))
+    }
+}
+
+unsafe impl<'ctx> BasicTypeData<'ctx> for AliasData<'ctx> {
+    const KIND: TypeKind = TypeKind::Alias;
+
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        self.aliased.layout(ctx)
+    }
+}
+
+impl<'ctx> AliasTy<'ctx> {
+    pub const fn name(self) -> Symbol<'ctx> {
+        self.get().name
+    }
+
+    /// The type this alias names, which may be another alias
+    pub const fn aliased(self) -> super::Type<'ctx> {
+        self.get().aliased
+    }
+}
+
+impl<'ctx> super::Type<'ctx> {
+    /// The type behind any aliases
+    pub fn strip_aliases(self) -> Self {
+        let mut ty = self;
+        while let Some(alias) = ty.try_cast::<AliasTy>() {
+            ty = alias.aliased();
+        }
+        ty
+    }
+}
+
+#[test]
+fn test_named_types() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let file = ctx.create_opaque("FILE");
+        assert!(matches!(file.layout(ctx), Ok(super::Layout::Unknown)));
+        assert_eq!(ctx.get_named("FILE"), Some(file));
+        assert_eq!(ctx.get_aggregate("FILE"), None);
+
+        let size_t = ctx.create_alias("size_t", ctx.intptr_ty());
+        let usize_t = ctx.create_alias("usize_t", size_t);
+        assert_ne!(size_t, ctx.intptr_ty());
+        assert_eq!(usize_t.strip_aliases(), ctx.intptr_ty());
+        assert_eq!(usize_t.cast::<AliasTy>().name().as_str(), "usize_t");
+        assert!(usize_t.structurally_equal(ctx.intptr_ty()));
+
+        // aliases have their own identity, so signatures using them are interned apart
+        let takes_size = ctx.function(ctx.unit_ty(), &[size_t]);
+        let takes_intptr = ctx.function(ctx.unit_ty(), &[ctx.intptr_ty()]);
+        assert_ne!(takes_size, takes_intptr);
+        assert!(takes_size.structurally_equal(takes_intptr));
+        assert_eq!(ctx.canonicalize().get(takes_size), Some(&takes_intptr));
+        match size_t.layout(ctx).unwrap() {
+            super::Layout::Concrete(layout) => assert_eq!(layout.size(), 8),
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+
+        // opaque types can only be used behind a pointer
+        let stream = ctx.create_aggregate(
+            "stream",
+            [super::AggregateField::new(ctx.symbol("file"), file)],
+        );
+        assert!(matches!(stream.layout(ctx), Ok(super::Layout::Unknown)));
+    });
+}
@@ -0,0 +1,127 @@
+use crate::Symbol;
+
+use super::Type;
+
+/// Metadata attached to a type, which doesn't affect its identity
+///
+/// Since function types are interned by signature, attributes on a
+/// [`FuncTy`](super::FuncTy) are shared by every use of that signature.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Attributes<'ctx> {
+    /// Where the type was declared
+    pub location: Option<SourceLocation<'ctx>>,
+    /// The name of the type in the source language, if it differs from the type's name
+    pub source_name: Option<Symbol<'ctx>>,
+    pub doc: Option<Symbol<'ctx>>,
+    pub flags: AttributeFlags,
+    /// Attributes which aren't known to the context, in the order they were added
+    pub custom: Vec<(Symbol<'ctx>, AttributeValue<'ctx>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation<'ctx> {
+    pub file: Symbol<'ctx>,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttributeFlags(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeValue<'ctx> {
+    Flag,
+    Int(i64),
+    Str(Symbol<'ctx>),
+    Type(Type<'ctx>),
+}
+
+impl AttributeFlags {
+    pub const NONE: Self = Self(0);
+    /// Values of this type should not be discarded
+    pub const MUST_USE: Self = Self(1 << 0);
+    pub const DEPRECATED: Self = Self(1 << 1);
+    /// More fields or variants may be added in the future
+    pub const NON_EXHAUSTIVE: Self = Self(1 << 2);
+    /// The type is generated by the compiler and has no declaration in the source
+    pub const SYNTHETIC: Self = Self(1 << 3);
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl core::ops::BitOr for AttributeFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+impl core::ops::BitOrAssign for AttributeFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+impl<'ctx> Attributes<'ctx> {
+    /// The value of the custom attribute `key`, if it was added
+    pub fn get(&self, key: Symbol<'ctx>) -> Option<AttributeValue<'ctx>> {
+        self.custom
+            .iter()
+            .find(|&&(name, _)| name == key)
+            .map(|&(_, value)| value)
+    }
+
+    /// Set the custom attribute `key`, replacing any previous value
+    pub fn set(&mut self, key: Symbol<'ctx>, value: AttributeValue<'ctx>) {
+        match self.custom.iter_mut().find(|(name, _)| *name == key) {
+            Some((_, old)) => *old = value,
+            None => self.custom.push((key, value)),
+        }
+    }
+}
+
+#[test]
+fn test_attributes() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let point = ctx.create_aggregate("point", []);
+        let func = ctx.function(point, &[]);
+        assert_eq!(ctx.attributes(point), None);
+
+        ctx.update_attributes(point, |attrs| {
+            attrs.location = Some(SourceLocation {
+                file: ctx.symbol("point.h"),
+                line: 3,
+                column: 1,
+            });
+            attrs.flags |= AttributeFlags::MUST_USE;
+            attrs.set(ctx.symbol("packed_by"), AttributeValue::Type(func));
+        });
+
+        let attrs = ctx.attributes(point).unwrap();
+        assert_eq!(attrs.location.unwrap().line, 3);
+        assert!(attrs.flags.contains(AttributeFlags::MUST_USE));
+        assert_eq!(
+            attrs.get(ctx.symbol("packed_by")),
+            Some(AttributeValue::Type(func))
+        );
+
+        // attributes don't change identity
+        assert_eq!(ctx.function(point, &[]), func);
+        assert_eq!(ctx.attributes(func), None);
+    });
+}
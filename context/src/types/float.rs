@@ -8,12 +8,141 @@ pub struct FloatData {
     pub kind: FloatKind,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FloatKind {
     Ieee16Bit,
     Ieee32Bit,
     Ieee64Bit,
     Ieee128Bit,
+    /// The x87 80-bit extended precision format, as used by `long double` on x86
+    X87Extended80Bit,
+}
+
+/// The encoding of a floating point format
+///
+/// Bit patterns are returned in the low bits of a `u128`, which is wide enough for
+/// every [`FloatKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FloatSemantics {
+    /// The number of bits in the encoding, including the sign
+    pub bits: u16,
+    pub exponent_bits: u16,
+    /// The number of bits in the significand field, which includes the integer bit if
+    /// it is stored explicitly
+    pub significand_bits: u16,
+    /// The number of significant binary digits, including the integer bit
+    pub precision: u16,
+    /// Whether the integer bit of the significand is stored, as in x87 floats
+    pub explicit_integer_bit: bool,
+    pub has_subnormals: bool,
+    /// Whether NaNs can carry a payload in the bits not used to mark them quiet
+    pub has_nan_payloads: bool,
+}
+
+impl FloatKind {
+    pub const fn semantics(self) -> FloatSemantics {
+        let (bits, exponent_bits, significand_bits, explicit_integer_bit) = match self {
+            Self::Ieee16Bit => (16, 5, 10, false),
+            Self::Ieee32Bit => (32, 8, 23, false),
+            Self::Ieee64Bit => (64, 11, 52, false),
+            Self::Ieee128Bit => (128, 15, 112, false),
+            Self::X87Extended80Bit => (80, 15, 64, true),
+        };
+
+        FloatSemantics {
+            bits,
+            exponent_bits,
+            significand_bits,
+            precision: if explicit_integer_bit {
+                significand_bits
+            } else {
+                significand_bits + 1
+            },
+            explicit_integer_bit,
+            has_subnormals: true,
+            has_nan_payloads: true,
+        }
+    }
+}
+
+impl FloatSemantics {
+    /// The amount added to the exponent in the encoding
+    pub const fn bias(&self) -> i32 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+
+    /// The exponent of the largest finite value
+    pub const fn max_exponent(&self) -> i32 {
+        self.bias()
+    }
+
+    /// The exponent of the smallest positive normal value
+    pub const fn min_exponent(&self) -> i32 {
+        1 - self.bias()
+    }
+
+    pub const fn sign_mask(&self) -> u128 {
+        1 << (self.bits - 1)
+    }
+
+    pub const fn exponent_mask(&self) -> u128 {
+        ((1 << self.exponent_bits) - 1) << self.significand_bits
+    }
+
+    pub const fn significand_mask(&self) -> u128 {
+        (1 << self.significand_bits) - 1
+    }
+
+    /// The encoding of a value with a biased exponent of `exponent`, and a significand
+    /// of 1 followed by the `fraction` bits
+    const fn encode(&self, exponent: u128, fraction: u128) -> u128 {
+        let integer_bit = if self.explicit_integer_bit {
+            1 << (self.significand_bits - 1)
+        } else {
+            0
+        };
+
+        (exponent << self.significand_bits) | integer_bit | fraction
+    }
+
+    /// The mask of the fraction bits, which excludes an explicit integer bit
+    const fn fraction_mask(&self) -> u128 {
+        (1 << (self.precision - 1)) - 1
+    }
+
+    pub const fn max_finite_bits(&self) -> u128 {
+        let max_exponent = (1 << self.exponent_bits) - 2;
+        self.encode(max_exponent, self.fraction_mask())
+    }
+
+    /// The most negative finite value
+    pub const fn min_finite_bits(&self) -> u128 {
+        self.sign_mask() | self.max_finite_bits()
+    }
+
+    pub const fn min_positive_normal_bits(&self) -> u128 {
+        self.encode(1, 0)
+    }
+
+    pub const fn min_positive_subnormal_bits(&self) -> u128 {
+        1
+    }
+
+    /// The difference between 1 and the next larger value
+    pub const fn epsilon_bits(&self) -> u128 {
+        let exponent = self.bias() + 1 - self.precision as i32;
+        self.encode(exponent as u128, 0)
+    }
+
+    pub const fn infinity_bits(&self) -> u128 {
+        self.encode((1 << self.exponent_bits) - 1, 0)
+    }
+
+    /// The quiet NaN with an empty payload
+    pub const fn quiet_nan_bits(&self) -> u128 {
+        let quiet_bit = 1 << (self.precision - 2);
+        self.encode((1 << self.exponent_bits) - 1, quiet_bit)
+    }
 }
 
 impl init::Ctor<FloatKind> for FloatData {
@@ -33,22 +162,31 @@ impl init::Ctor<FloatKind> for FloatData {
 unsafe impl<'ctx> BasicTypeData<'ctx> for FloatData {
     const KIND: TypeKind = TypeKind::Float;
 
-    fn layout(&self, _ctx: crate::Context<'ctx>) -> super::raw::Layout {
-        match self.kind {
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        let layout = match self.kind {
             FloatKind::Ieee16Bit => {
-                super::raw::Layout::Concrete(super::raw::ConcreteLayout { size: 2, align: 2 })
+                super::raw::Layout::Concrete(super::raw::ConcreteLayout::new(2, 2))
             }
             FloatKind::Ieee32Bit => {
-                super::raw::Layout::Concrete(super::raw::ConcreteLayout { size: 4, align: 4 })
+                super::raw::Layout::Concrete(super::raw::ConcreteLayout::new(4, 4))
             }
             FloatKind::Ieee64Bit => {
-                super::raw::Layout::Concrete(super::raw::ConcreteLayout { size: 8, align: 8 })
+                super::raw::Layout::Concrete(super::raw::ConcreteLayout::new(8, 8))
             }
-            FloatKind::Ieee128Bit => super::raw::Layout::Concrete(super::raw::ConcreteLayout {
-                size: 16,
-                align: 16,
-            }),
-        }
+            FloatKind::Ieee128Bit => {
+                super::raw::Layout::Concrete(super::raw::ConcreteLayout::new(16, 16))
+            }
+            // only 10 bytes are stored, the rest is padding up to the target's alignment
+            FloatKind::X87Extended80Bit => {
+                let align = 1 << ctx.target().x87_float_align_log2;
+                super::raw::Layout::Concrete(super::raw::ConcreteLayout::from_bits(80, align))
+            }
+        };
+
+        Ok(layout)
     }
 }
 
@@ -56,4 +194,79 @@ impl FloatTy<'_> {
     pub const fn float_kind(self) -> FloatKind {
         self.get().kind
     }
+
+    pub const fn semantics(self) -> FloatSemantics {
+        self.get().kind.semantics()
+    }
+}
+
+#[test]
+fn test_x87_sizes() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let super::raw::Layout::Concrete(x87) = ctx.float_x87_ty().layout(ctx).unwrap() else {
+            panic!("expected a concrete layout");
+        };
+        assert_eq!(
+            (x87.bit_size(), x87.store_size(), x87.alloc_size()),
+            (80, 10, 16)
+        );
+    });
+
+    let i386 = crate::TargetSpec::builder().pointer(4, 2).build().unwrap();
+    crate::Context::with(i386, |ctx| {
+        let super::raw::Layout::Concrete(x87) = ctx.float_x87_ty().layout(ctx).unwrap() else {
+            panic!("expected a concrete layout");
+        };
+        assert_eq!((x87.store_size(), x87.alloc_size(), x87.align), (10, 12, 4));
+    });
+}
+
+#[test]
+fn test_float_semantics() {
+    let check = |kind: FloatKind, max: u128, min_normal: u128, epsilon: u128, nan: u128| {
+        let semantics = kind.semantics();
+        assert_eq!(semantics.max_finite_bits(), max, "{kind:?}");
+        assert_eq!(semantics.min_positive_normal_bits(), min_normal, "{kind:?}");
+        assert_eq!(semantics.epsilon_bits(), epsilon, "{kind:?}");
+        assert_eq!(semantics.quiet_nan_bits(), nan, "{kind:?}");
+    };
+
+    check(FloatKind::Ieee16Bit, 0x7bff, 0x0400, 0x1400, 0x7e00);
+    check(
+        FloatKind::Ieee32Bit,
+        f32::MAX.to_bits().into(),
+        f32::MIN_POSITIVE.to_bits().into(),
+        f32::EPSILON.to_bits().into(),
+        f32::NAN.to_bits().into(),
+    );
+    check(
+        FloatKind::Ieee64Bit,
+        f64::MAX.to_bits().into(),
+        f64::MIN_POSITIVE.to_bits().into(),
+        f64::EPSILON.to_bits().into(),
+        f64::NAN.to_bits().into(),
+    );
+    check(
+        FloatKind::Ieee128Bit,
+        0x7ffe_ffff_ffff_ffff_ffff_ffff_ffff_ffff,
+        0x0001_0000_0000_0000_0000_0000_0000_0000,
+        0x3f8f_0000_0000_0000_0000_0000_0000_0000,
+        0x7fff_8000_0000_0000_0000_0000_0000_0000,
+    );
+    check(
+        FloatKind::X87Extended80Bit,
+        0x7ffe_ffff_ffff_ffff_ffff,
+        0x0001_8000_0000_0000_0000,
+        0x3fc0_8000_0000_0000_0000,
+        0x7fff_c000_0000_0000_0000,
+    );
+
+    let x87 = FloatKind::X87Extended80Bit.semantics();
+    assert_eq!(x87.precision, 64);
+    assert_eq!((x87.min_exponent(), x87.max_exponent()), (-16382, 16383));
+    assert_eq!(x87.infinity_bits(), 0x7fff_8000_0000_0000_0000);
+    assert_eq!(
+        FloatKind::Ieee64Bit.semantics().min_finite_bits(),
+        u128::from(f64::MIN.to_bits())
+    );
 }
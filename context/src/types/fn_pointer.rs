@@ -0,0 +1,155 @@
+use super::raw::{BasicTypeData, RawType, TypeHeader, TypeKind};
+
+pub type FnPointerTy<'ctx> = RawType<'ctx, FnPointerData<'ctx>>;
+
+#[repr(C)]
+pub struct FnPointerData<'ctx> {
+    header: TypeHeader,
+    pub func: super::FuncTy<'ctx>,
+}
+
+impl<'ctx> init::Ctor<super::FuncTy<'ctx>> for FnPointerData<'ctx> {
+    type Error = core::convert::Infallible;
+
+    fn try_init(
+        ptr: init::ptr::Uninit<Self>,
+        func: super::FuncTy<'ctx>,
+    ) -> Result<init::ptr::Init<Self>, Self::Error> {
+        Ok(ptr.write(Self {
+            header: TypeHeader::of::<Self>(),
+            func,
+        }))
+    }
+}
+
+unsafe impl<'ctx> BasicTypeData<'ctx> for FnPointerData<'ctx> {
+    const KIND: TypeKind = TypeKind::FnPointer;
+
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        let target = ctx.target();
+        let code_size = u64::from(target.code_pointer_size_bytes);
+        let code_align = 1 << target.code_pointer_align_log2;
+        let data_size = u64::from(target.pointer_size_bytes);
+        let data_align = 1 << target.pointer_align_log2;
+
+        let (size, align) = match target.function_pointer {
+            crate::FunctionPointerRepr::Address => (code_size, code_align),
+            crate::FunctionPointerRepr::Descriptor => (data_size, data_align),
+            crate::FunctionPointerRepr::InlineDescriptor { .. } => {
+                // the entry point, then the rest of the descriptor as data words
+                let align = code_align.max(data_align);
+                let size = inline_data_words(target)
+                    .last()
+                    .map_or(code_size, |offset| offset + data_size);
+                (size.next_multiple_of(align), align)
+            }
+        };
+
+        let layout = super::raw::ConcreteLayout::new(size, align);
+        Ok(super::raw::Layout::Concrete(layout.limit(target)?))
+    }
+}
+
+/// The byte offsets of the data words after the entry point of an inline descriptor,
+/// which there are none of for other representations
+pub(super) fn inline_data_words(target: &crate::TargetSpec) -> impl Iterator<Item = u64> {
+    let words = match target.function_pointer {
+        crate::FunctionPointerRepr::InlineDescriptor { words } => words - 1,
+        crate::FunctionPointerRepr::Address | crate::FunctionPointerRepr::Descriptor => 0,
+    };
+    let data_size = u64::from(target.pointer_size_bytes);
+    let data_align = 1 << target.pointer_align_log2;
+    let start = u64::from(target.code_pointer_size_bytes).next_multiple_of(data_align);
+    (0..u64::from(words)).map(move |word| start + word * data_size)
+}
+
+impl<'ctx> FnPointerTy<'ctx> {
+    pub const fn func(self) -> super::FuncTy<'ctx> {
+        self.get().func
+    }
+}
+
+#[test]
+fn test_harvard_layout() {
+    let avr = crate::TargetSpec {
+        pointer_size_bytes: 2,
+        pointer_align_log2: 0,
+        pointer_diff_size_bytes: 2,
+        pointer_diff_align_log2: 0,
+        code_pointer_size_bytes: 4,
+        code_pointer_align_log2: 0,
+        function_pointer: crate::FunctionPointerRepr::Address,
+        int_layout: crate::IntLayoutRule::LEGACY,
+        c_data_model: crate::CDataModel::ILP32,
+        x87_float_align_log2: 2,
+    };
+
+    crate::Context::with(avr, |ctx| {
+        let func = ctx.function(ctx.unit_ty(), &[ctx.int_ty(8)]);
+        let callbacks = ctx.create_aggregate(
+            "callbacks",
+            [
+                super::AggregateField::new(ctx.symbol("data"), ctx.pointer_ty()),
+                super::AggregateField::new(ctx.symbol("on_event"), ctx.fn_ptr_ty(func)),
+            ],
+        );
+
+        match callbacks.layout(ctx).unwrap() {
+            super::raw::Layout::Concrete(layout) => {
+                assert_eq!(layout.size, 6);
+                assert_eq!(layout.align, 1);
+            }
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+    })
+}
+
+#[test]
+fn test_descriptor_layout() {
+    let ppc64 = crate::TargetSpec {
+        code_pointer_size_bytes: 8,
+        code_pointer_align_log2: 3,
+        function_pointer: crate::FunctionPointerRepr::InlineDescriptor { words: 2 },
+        ..crate::TEST_TARGET_SPEC
+    };
+
+    crate::Context::with(ppc64, |ctx| {
+        let func = ctx.function(ctx.unit_ty(), &[]);
+        let fn_ptr = ctx.fn_ptr_ty(func);
+        assert!(fn_ptr == ctx.fn_ptr_ty(func));
+
+        match fn_ptr.layout(ctx).unwrap() {
+            super::raw::Layout::Concrete(layout) => {
+                assert_eq!(layout.size, 16);
+                assert_eq!(layout.align, 8);
+            }
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+    });
+
+    // a 4-byte code pointer followed by two 2-byte data words
+    let inline = crate::TargetSpec {
+        pointer_size_bytes: 2,
+        pointer_align_log2: 0,
+        pointer_diff_size_bytes: 2,
+        pointer_diff_align_log2: 0,
+        code_pointer_size_bytes: 4,
+        code_pointer_align_log2: 1,
+        function_pointer: crate::FunctionPointerRepr::InlineDescriptor { words: 3 },
+        ..crate::TEST_TARGET_SPEC
+    };
+
+    crate::Context::with(inline, |ctx| {
+        let fn_ptr = ctx.fn_ptr_ty(ctx.function(ctx.unit_ty(), &[]));
+        match fn_ptr.layout(ctx).unwrap() {
+            super::raw::Layout::Concrete(layout) => {
+                assert_eq!(layout.size, 8);
+                assert_eq!(layout.align, 2);
+            }
+            layout => panic!("expected a concrete layout, found {layout:?}"),
+        }
+    })
+}
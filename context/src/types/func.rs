@@ -81,8 +81,11 @@ impl<'ctx> FuncData<'ctx> {
 unsafe impl<'ctx> BasicTypeData<'ctx> for FuncData<'ctx> {
     const KIND: TypeKind = TypeKind::Func;
 
-    fn layout(&self, _ctx: crate::Context<'ctx>) -> super::raw::Layout {
-        super::raw::Layout::Unknown
+    fn layout(
+        &self,
+        _ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        Ok(super::raw::Layout::Unknown)
     }
 }
 
@@ -131,7 +134,7 @@ unsafe impl<'ctx, I> init::layout_provider::LayoutProvider<FuncData<'ctx>, FuncD
     fn layout_for(args: &FuncDataInit<I>) -> Option<std::alloc::Layout> {
         let layout = Layout::new::<TypeHeader>();
         let (layout, _) = layout.extend(Layout::new::<usize>()).ok()?;
-        let (layout, _) = layout.extend(Layout::new::<istr::IBytes>()).ok()?;
+        let (layout, _) = layout.extend(Layout::new::<super::Type<'ctx>>()).ok()?;
         let (layout, _) = layout
             .extend(Layout::array::<super::Type<'ctx>>(args.len).ok()?)
             .ok()?;
@@ -27,20 +27,23 @@ impl init::Ctor<u16> for IntData {
 unsafe impl<'ctx> BasicTypeData<'ctx> for IntData {
     const KIND: TypeKind = TypeKind::Int;
 
-    fn layout(&self, _ctx: crate::Context<'ctx>) -> super::raw::Layout {
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
         let size = self.bits.get().div_ceil(8);
-        let align = if self.bits.get() % 8 == 0 {
-            self.bits.get() / 8
-        } else {
-            1
+        let align = match ctx.target().int_layout {
+            crate::IntLayoutRule::Legacy { align_log2 } => {
+                // index the alignments by the log2 of the rounded up size in bytes
+                let index = size.next_power_of_two().trailing_zeros() as usize;
+                1 << align_log2[index.min(align_log2.len() - 1)]
+            }
+            crate::IntLayoutRule::X86_64BitInt => size.next_power_of_two().min(8),
+            crate::IntLayoutRule::Aarch64BitInt => size.next_power_of_two().min(16),
         };
-        let align = (align / 2 + 1).next_power_of_two();
-        let align = crate::utils::gcd(size, align);
 
-        super::raw::Layout::Concrete(super::raw::ConcreteLayout {
-            size: size as u64,
-            align: align as u64,
-        })
+        let layout = super::raw::ConcreteLayout::from_bits(self.bits.get().into(), align.into());
+        Ok(super::raw::Layout::Concrete(layout.limit(ctx.target())?))
     }
 }
 
@@ -56,3 +59,78 @@ fn test() {
         dbg!(ctx.int_ty(48).cast::<IntTy>().get().layout(ctx));
     })
 }
+
+#[test]
+fn test_int_layout() {
+    // (bits, size, align) as reported by clang for `_BitInt(bits)` under the psABI
+    // rules, and by LLVM's `DataLayout` for `iN` under the legacy rule
+    let check = |rule, expected: &[(u16, u64, u64)]| {
+        let target = crate::TargetSpec {
+            int_layout: rule,
+            ..crate::TEST_TARGET_SPEC
+        };
+
+        crate::Context::with(target, |ctx| {
+            for &(bits, size, align) in expected {
+                match ctx.int_ty(bits).layout(ctx).unwrap() {
+                    super::raw::Layout::Concrete(layout) => {
+                        assert_eq!((layout.size(), layout.align()), (size, align), "i{bits}");
+                        assert_eq!(layout.bit_size(), u64::from(bits));
+                    }
+                    layout => panic!("expected a concrete layout, found {layout:?}"),
+                }
+            }
+        })
+    };
+
+    check(
+        crate::IntLayoutRule::LEGACY,
+        &[
+            (7, 1, 1),
+            (24, 4, 4),
+            (48, 8, 8),
+            (64, 8, 8),
+            (65, 16, 16),
+            (128, 16, 16),
+            (129, 32, 16),
+            (4000, 512, 16),
+        ],
+    );
+    // i386, where `i64` is aligned to 4 bytes
+    check(
+        crate::IntLayoutRule::Legacy {
+            align_log2: [0, 1, 2, 2, 4],
+        },
+        &[(48, 8, 4), (64, 8, 4), (65, 16, 16), (4000, 512, 16)],
+    );
+    // a datalayout without `i128`, so wider integers are aligned like `i64`
+    check(
+        crate::IntLayoutRule::Legacy {
+            align_log2: [0, 1, 2, 3, 3],
+        },
+        &[(65, 16, 8), (128, 16, 8), (129, 24, 8), (4000, 504, 8)],
+    );
+    check(
+        crate::IntLayoutRule::X86_64BitInt,
+        &[
+            (7, 1, 1),
+            (24, 4, 4),
+            (64, 8, 8),
+            (65, 16, 8),
+            (128, 16, 8),
+            (129, 24, 8),
+            (4000, 504, 8),
+        ],
+    );
+    check(
+        crate::IntLayoutRule::Aarch64BitInt,
+        &[
+            (7, 1, 1),
+            (24, 4, 4),
+            (65, 16, 16),
+            (128, 16, 16),
+            (129, 32, 16),
+            (4000, 512, 16),
+        ],
+    );
+}
@@ -0,0 +1,237 @@
+use super::Type;
+
+/// A map from types to values, iterated in the order the types were created
+///
+/// Entries are stored densely by type index, so lookups are a single bounds check
+/// and memory use is proportional to the newest type in the map.
+pub struct TypeMap<'ctx, V> {
+    entries: Vec<Option<(Type<'ctx>, V)>>,
+    len: usize,
+}
+
+/// A set of types, iterated in the order the types were created
+#[derive(Default)]
+pub struct TypeSet<'ctx> {
+    map: TypeMap<'ctx, ()>,
+}
+
+impl<V> Default for TypeMap<'_, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'ctx, V> TypeMap<'ctx, V> {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, ty: Type<'ctx>) -> Option<&V> {
+        let (_, value) = self.entries.get(ty.index() as usize)?.as_ref()?;
+        Some(value)
+    }
+
+    pub fn get_mut(&mut self, ty: Type<'ctx>) -> Option<&mut V> {
+        let (_, value) = self.entries.get_mut(ty.index() as usize)?.as_mut()?;
+        Some(value)
+    }
+
+    pub fn contains_key(&self, ty: Type<'ctx>) -> bool {
+        self.get(ty).is_some()
+    }
+
+    pub fn insert(&mut self, ty: Type<'ctx>, value: V) -> Option<V> {
+        let index = ty.index() as usize;
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || None);
+        }
+
+        let old = self.entries[index].replace((ty, value));
+        if old.is_none() {
+            self.len += 1;
+        }
+        old.map(|(_, value)| value)
+    }
+
+    pub fn get_or_insert_with(&mut self, ty: Type<'ctx>, f: impl FnOnce() -> V) -> &mut V {
+        if !self.contains_key(ty) {
+            self.insert(ty, f());
+        }
+
+        self.get_mut(ty).unwrap()
+    }
+
+    pub fn remove(&mut self, ty: Type<'ctx>) -> Option<V> {
+        let (_, value) = self.entries.get_mut(ty.index() as usize)?.take()?;
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.len = 0;
+    }
+
+    /// Remove every type with an index of at least `len`
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+        self.len = self.entries.iter().filter(|entry| entry.is_some()).count();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Type<'ctx>, &V)> + '_ {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.as_ref().map(|(ty, value)| (*ty, value)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Type<'ctx>, &mut V)> + '_ {
+        self.entries
+            .iter_mut()
+            .filter_map(|entry| entry.as_mut().map(|(ty, value)| (*ty, value)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = Type<'ctx>> + '_ {
+        self.iter().map(|(ty, _)| ty)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+impl<'ctx> TypeSet<'ctx> {
+    pub const fn new() -> Self {
+        Self {
+            map: TypeMap::new(),
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn contains(&self, ty: Type<'ctx>) -> bool {
+        self.map.contains_key(ty)
+    }
+
+    /// Add `ty` to the set, returns true if it wasn't already present
+    pub fn insert(&mut self, ty: Type<'ctx>) -> bool {
+        self.map.insert(ty, ()).is_none()
+    }
+
+    /// Remove `ty` from the set, returns true if it was present
+    pub fn remove(&mut self, ty: Type<'ctx>) -> bool {
+        self.map.remove(ty).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Type<'ctx>> + '_ {
+        self.map.keys()
+    }
+}
+
+impl<V: Clone> Clone for TypeMap<'_, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl Clone for TypeSet<'_> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<V: core::fmt::Debug> core::fmt::Debug for TypeMap<'_, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl core::fmt::Debug for TypeSet<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<'ctx, V> FromIterator<(Type<'ctx>, V)> for TypeMap<'ctx, V> {
+    fn from_iter<I: IntoIterator<Item = (Type<'ctx>, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'ctx, V> Extend<(Type<'ctx>, V)> for TypeMap<'ctx, V> {
+    fn extend<I: IntoIterator<Item = (Type<'ctx>, V)>>(&mut self, iter: I) {
+        for (ty, value) in iter {
+            self.insert(ty, value);
+        }
+    }
+}
+
+impl<'ctx> FromIterator<Type<'ctx>> for TypeSet<'ctx> {
+    fn from_iter<I: IntoIterator<Item = Type<'ctx>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'ctx> Extend<Type<'ctx>> for TypeSet<'ctx> {
+    fn extend<I: IntoIterator<Item = Type<'ctx>>>(&mut self, iter: I) {
+        for ty in iter {
+            self.insert(ty);
+        }
+    }
+}
+
+#[test]
+fn test_type_map() {
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let int = ctx.int_ty(32);
+        let ptr = ctx.pointer_ty();
+        let func = ctx.function(int, &[ptr]);
+
+        let mut map = TypeMap::new();
+        map.insert(func, "func");
+        map.insert(int, "int");
+        map.insert(ptr, "ptr");
+        assert_eq!(map.insert(int, "i32"), Some("int"));
+        assert_eq!(map.len(), 3);
+
+        // iteration follows creation order, not insertion order
+        let order = map.iter().map(|(_, name)| *name).collect::<Vec<_>>();
+        assert_eq!(order, ["ptr", "i32", "func"]);
+
+        assert_eq!(map.remove(ptr), Some("ptr"));
+        assert_eq!(map.get(ptr), None);
+
+        let set = [func, int, func].into_iter().collect::<TypeSet>();
+        assert_eq!(set.len(), 2);
+        assert!(set.iter().eq([int, func]));
+    });
+}
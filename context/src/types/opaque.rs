@@ -0,0 +1,45 @@
+use crate::Symbol;
+
+use super::raw::{BasicTypeData, RawType, TypeHeader, TypeKind};
+
+/// A named type without a body, like a C incomplete struct or a Rust `extern type`
+///
+/// Its layout is always unknown, so it can only be used behind a pointer.
+pub type OpaqueTy<'ctx> = RawType<'ctx, OpaqueData<'ctx>>;
+
+#[repr(C)]
+pub struct OpaqueData<'ctx> {
+    header: TypeHeader,
+    pub name: Symbol<'ctx>,
+}
+
+impl<'ctx> init::Ctor<Symbol<'ctx>> for OpaqueData<'ctx> {
+    type Error = core::convert::Infallible;
+
+    fn try_init(
+        ptr: init::ptr::Uninit<Self>,
+        name: Symbol<'ctx>,
+    ) -> Result<init::ptr::Init<Self>, Self::Error> {
+        Ok(ptr.write(Self {
+            header: TypeHeader::of::<Self>(),
+            name,
+        }))
+    }
+}
+
+unsafe impl<'ctx> BasicTypeData<'ctx> for OpaqueData<'ctx> {
+    const KIND: TypeKind = TypeKind::Opaque;
+
+    fn layout(
+        &self,
+        _ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        Ok(super::raw::Layout::Unknown)
+    }
+}
+
+impl<'ctx> OpaqueTy<'ctx> {
+    pub const fn name(self) -> Symbol<'ctx> {
+        self.get().name
+    }
+}
@@ -0,0 +1,43 @@
+use super::raw::{BasicTypeData, RawType, TypeHeader, TypeKind};
+
+/// A placeholder for the type argument at `index`, which is replaced by
+/// [`Context::instantiate`](crate::Context::instantiate)
+pub type ParamTy<'ctx> = RawType<'ctx, ParamData>;
+
+#[repr(C)]
+pub struct ParamData {
+    header: TypeHeader,
+    pub index: u32,
+}
+
+impl init::Ctor<u32> for ParamData {
+    type Error = core::convert::Infallible;
+
+    fn try_init(
+        ptr: init::ptr::Uninit<Self>,
+        index: u32,
+    ) -> Result<init::ptr::Init<Self>, Self::Error> {
+        Ok(ptr.write(Self {
+            header: TypeHeader::of::<Self>(),
+            index,
+        }))
+    }
+}
+
+unsafe impl<'ctx> BasicTypeData<'ctx> for ParamData {
+    const KIND: TypeKind = TypeKind::Param;
+
+    fn layout(
+        &self,
+        _ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
+        // the layout depends on the type this is substituted with
+        Ok(super::raw::Layout::Unknown)
+    }
+}
+
+impl ParamTy<'_> {
+    pub const fn param_index(self) -> u32 {
+        self.get().index
+    }
+}
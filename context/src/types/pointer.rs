@@ -1,21 +1,27 @@
 use super::raw::{BasicTypeData, RawType, TypeHeader, TypeKind};
 
+/// An untyped data pointer into an address space
+///
+/// Address space 0 is the default. Other address spaces are target or runtime defined,
+/// for example to tell pointers to garbage collected memory apart from other pointers.
 pub type PointerTy<'ctx> = RawType<'ctx, PointerData>;
 
 #[repr(C)]
 pub struct PointerData {
     header: TypeHeader,
+    pub address_space: u32,
 }
 
-impl init::Ctor for PointerData {
+impl init::Ctor<u32> for PointerData {
     type Error = core::convert::Infallible;
 
     fn try_init(
         ptr: init::ptr::Uninit<Self>,
-        (): (),
+        address_space: u32,
     ) -> Result<init::ptr::Init<Self>, Self::Error> {
         Ok(ptr.write(Self {
             header: TypeHeader::of::<Self>(),
+            address_space,
         }))
     }
 }
@@ -23,9 +29,20 @@ impl init::Ctor for PointerData {
 unsafe impl<'ctx> BasicTypeData<'ctx> for PointerData {
     const KIND: TypeKind = TypeKind::Pointer;
 
-    fn layout(&self, ctx: crate::Context<'ctx>) -> super::raw::Layout {
+    fn layout(
+        &self,
+        ctx: crate::Context<'ctx>,
+    ) -> Result<super::raw::Layout, super::raw::LayoutError> {
         let size = ctx.target().pointer_size_bytes as u64;
         let align = 1 << ctx.target().pointer_align_log2;
-        super::raw::Layout::Concrete(super::raw::ConcreteLayout { size, align })
+        Ok(super::raw::Layout::Concrete(
+            super::raw::ConcreteLayout::new(size, align),
+        ))
+    }
+}
+
+impl PointerTy<'_> {
+    pub const fn address_space(self) -> u32 {
+        self.get().address_space
     }
 }
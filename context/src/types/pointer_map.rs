@@ -0,0 +1,232 @@
+use super::{raw::Layout, AggregateTy, LayoutError, PointerTy, Type, TypeKind};
+
+/// The pointer-sized words of a type that hold pointers, as needed by a precise garbage
+/// collector
+///
+/// Bit `i` of the map is set if the word at byte offset `i * word_size` is a pointer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerMap {
+    word_size: u64,
+    words: u64,
+    bits: Box<[u64]>,
+}
+
+impl PointerMap {
+    fn new(word_size: u64, size: u64) -> Self {
+        let words = size.div_ceil(word_size);
+        Self {
+            word_size,
+            words,
+            bits: vec![0; words.div_ceil(64) as usize].into_boxed_slice(),
+        }
+    }
+
+    /// The size of a word in bytes, which is the size of a pointer on the target
+    pub const fn word_size(&self) -> u64 {
+        self.word_size
+    }
+
+    /// The number of words the type covers
+    pub const fn len(&self) -> u64 {
+        self.words
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.words == 0
+    }
+
+    /// The map itself, word `i` is bit `i % 64` of element `i / 64`
+    pub fn as_bits(&self) -> &[u64] {
+        &self.bits
+    }
+
+    pub fn is_pointer(&self, word: u64) -> bool {
+        word < self.words && self.bits[(word / 64) as usize] & (1 << (word % 64)) != 0
+    }
+
+    pub fn has_pointers(&self) -> bool {
+        self.bits.iter().any(|&bits| bits != 0)
+    }
+
+    /// The byte offsets of every pointer, in increasing order
+    pub fn offsets(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.words)
+            .filter(|&word| self.is_pointer(word))
+            .map(|word| word * self.word_size)
+    }
+
+    fn mark<'ctx>(
+        &mut self,
+        ctx: crate::Context<'ctx>,
+        ty: Type<'ctx>,
+        offset: u64,
+        address_space: Option<u32>,
+    ) -> Result<(), LayoutError> {
+        let ty = ty.strip_aliases();
+
+        match ty.kind() {
+            TypeKind::Pointer => {
+                let space = ty.cast::<PointerTy>().address_space();
+                if address_space.is_none_or(|address_space| address_space == space) {
+                    self.mark_pointer(offset)?;
+                }
+            }
+            // the entry point is code, which isn't managed, but the data words of an
+            // inline descriptor are ordinary pointers in the default address space
+            TypeKind::FnPointer => {
+                if address_space.is_none_or(|address_space| address_space == 0) {
+                    for data_offset in super::fn_pointer::inline_data_words(ctx.target()) {
+                        self.mark_pointer(offset + data_offset)?;
+                    }
+                }
+            }
+            TypeKind::Aggregate => {
+                let aggregate = ty.cast::<AggregateTy>();
+                // the caller already checked that the layout is concrete
+                let layouts = aggregate.field_layouts(ctx)?.unwrap_or_default();
+
+                for (field, layout) in aggregate.fields().iter().zip(layouts) {
+                    // bitfields can't hold pointers
+                    if layout.bitfield.is_none() {
+                        self.mark(ctx, field.ty, offset + layout.offset, address_space)?;
+                    }
+                }
+            }
+            TypeKind::Unit
+            | TypeKind::Int
+            | TypeKind::Float
+            | TypeKind::Func
+            | TypeKind::Param
+            | TypeKind::Opaque
+            | TypeKind::Alias => (),
+        }
+
+        Ok(())
+    }
+
+    fn mark_pointer(&mut self, offset: u64) -> Result<(), LayoutError> {
+        if offset % self.word_size != 0 {
+            return Err(LayoutError::MisalignedPointer { offset });
+        }
+
+        let word = offset / self.word_size;
+        self.bits[(word / 64) as usize] |= 1 << (word % 64);
+        Ok(())
+    }
+}
+
+impl<'ctx> Type<'ctx> {
+    /// The words of this type that hold pointers, or `None` if its layout isn't known
+    ///
+    /// If `address_space` is given, only pointers into that address space are included,
+    /// so pointers into memory the collector doesn't manage can be left out. The map is
+    /// computed once for each type and address space.
+    pub fn pointer_map(
+        self,
+        ctx: crate::Context<'ctx>,
+        address_space: Option<u32>,
+    ) -> Result<Option<&'ctx PointerMap>, LayoutError> {
+        if let Some(map) = ctx.type_ctx().get_pointer_map(self, address_space) {
+            return Ok(Some(map));
+        }
+
+        let layout = match self.layout(ctx)? {
+            Layout::Concrete(layout) => layout,
+            Layout::RuntimeKnown | Layout::Unknown => return Ok(None),
+        };
+
+        let word_size = u64::from(ctx.target().pointer_size_bytes);
+        let mut map = PointerMap::new(word_size, layout.size());
+        map.mark(ctx, self, 0, address_space)?;
+
+        Ok(Some(ctx.type_ctx().insert_pointer_map(
+            self,
+            address_space,
+            map,
+        )))
+    }
+}
+
+#[test]
+fn test_pointer_map() {
+    use super::AggregateField;
+
+    const GC: u32 = 1;
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let field = |ty| AggregateField::new(ctx.symbol("field"), ty);
+        let int = ctx.int_ty(32);
+        let gc_ptr = ctx.pointer_ty_in(GC);
+        assert_eq!(gc_ptr, ctx.pointer_ty_in(GC));
+        assert_ne!(gc_ptr, ctx.pointer_ty());
+
+        // struct node { int tag; node __gc *next; void *data; }
+        let node =
+            ctx.create_aggregate("node", [field(int), field(gc_ptr), field(ctx.pointer_ty())]);
+        // struct pair { node a; int x; node b; }
+        let pair = ctx.create_aggregate("pair", [field(node), field(int), field(node)]);
+
+        let map = pair.pointer_map(ctx, Some(GC)).unwrap().unwrap();
+        assert_eq!(map.len(), 7);
+        assert!(map.offsets().eq([8, 40]));
+        assert_eq!(map.as_bits(), [0b10_0010]);
+        assert!(core::ptr::eq(
+            map,
+            pair.pointer_map(ctx, Some(GC)).unwrap().unwrap()
+        ));
+
+        let all = pair.pointer_map(ctx, None).unwrap().unwrap();
+        assert!(all.offsets().eq([8, 16, 40, 48]));
+
+        assert!(!int.pointer_map(ctx, None).unwrap().unwrap().has_pointers());
+        assert!(ctx
+            .create_opaque("handle")
+            .pointer_map(ctx, None)
+            .unwrap()
+            .is_none());
+    });
+}
+
+#[test]
+fn test_descriptor_pointer_map() {
+    // a code pointer followed by a data word, such as a pointer to the function's
+    // environment
+    let inline = crate::TargetSpec {
+        function_pointer: crate::FunctionPointerRepr::InlineDescriptor { words: 2 },
+        ..crate::TEST_TARGET_SPEC
+    };
+
+    crate::Context::with(inline, |ctx| {
+        let callback = ctx.fn_ptr_ty(ctx.function(ctx.unit_ty(), &[]));
+        let handler = ctx.create_aggregate(
+            "handler",
+            [
+                super::AggregateField::new(ctx.symbol("tag"), ctx.int_ty(64)),
+                super::AggregateField::new(ctx.symbol("callback"), callback),
+            ],
+        );
+
+        let map = handler.pointer_map(ctx, None).unwrap().unwrap();
+        assert!(map.offsets().eq([16]));
+        assert!(map.offsets().eq(handler
+            .pointer_map(ctx, Some(0))
+            .unwrap()
+            .unwrap()
+            .offsets()));
+        assert!(!handler
+            .pointer_map(ctx, Some(1))
+            .unwrap()
+            .unwrap()
+            .has_pointers());
+    });
+
+    // a plain code pointer holds nothing the collector manages
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let callback = ctx.fn_ptr_ty(ctx.function(ctx.unit_ty(), &[]));
+        assert!(!callback
+            .pointer_map(ctx, None)
+            .unwrap()
+            .unwrap()
+            .has_pointers());
+    });
+}
@@ -1,4 +1,4 @@
-use std::hash::Hash;
+use std::{cell::Cell, hash::Hash};
 
 use crate::{
     ctx::{AllocContext, ContextId},
@@ -8,11 +8,16 @@ use crate::{
 
 pub struct TypeHeader {
     kind: TypeKind,
+    // assigned when the type is registered with its context
+    index: Cell<u32>,
 }
 
 impl TypeHeader {
     pub const fn of<'ctx, T: ?Sized + BasicTypeData<'ctx>>() -> Self {
-        Self { kind: T::KIND }
+        Self {
+            kind: T::KIND,
+            index: Cell::new(u32::MAX),
+        }
     }
 }
 
@@ -24,12 +29,122 @@ pub enum Layout {
     Unknown,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    /// An alignment override is not a power of two
+    InvalidAlignment(u64),
+    /// The field at `field` is placed at an offset that isn't a multiple of its alignment
+    MisalignedField {
+        field: usize,
+        offset: u64,
+        align: u64,
+    },
+    /// The fields at `first` and `second` occupy some of the same bits
+    OverlappingFields { first: usize, second: usize },
+    /// The type is larger than the target's
+    /// [maximum object size](crate::TargetSpec::max_object_size)
+    TooLarge { max_size: u64 },
+    /// A pointer at byte `offset` isn't aligned to a pointer-sized word, so it can't
+    /// be described by a [`PointerMap`](super::PointerMap)
+    MisalignedPointer { offset: u64 },
+}
+
+impl core::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Self::InvalidAlignment(align) => {
+                write!(f, "alignment {align} is not a power of two")
+            }
+            Self::MisalignedField {
+                field,
+                offset,
+                align,
+            } => write!(
+                f,
+                "field {field} at offset {offset} is not aligned to {align} bytes"
+            ),
+            Self::OverlappingFields { first, second } => {
+                write!(f, "fields {first} and {second} overlap")
+            }
+            Self::TooLarge { max_size } => {
+                write!(f, "type is larger than the maximum of {max_size} bytes")
+            }
+            Self::MisalignedPointer { offset } => {
+                write!(f, "pointer at offset {offset} is not aligned to a word")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// The size and alignment of a type with a known layout
+///
+/// Like in LLVM, a type has three sizes: the number of bits in a value, the number of
+/// bytes a store writes, and the number of bytes between consecutive elements of an
+/// array, which includes any tail padding. An `i1` has a bit size of 1, and an x87
+/// float has a store size of 10 and an alloc size of 16.
 #[derive(Debug, Clone, Copy)]
 pub struct ConcreteLayout {
+    pub(crate) bits: u64,
     pub(crate) size: u64,
     pub(crate) align: u64,
 }
 
+impl ConcreteLayout {
+    /// The layout of a type which fills all `size` bytes
+    pub(crate) const fn new(size: u64, align: u64) -> Self {
+        Self {
+            bits: size * 8,
+            size,
+            align,
+        }
+    }
+
+    /// The layout of a type with `bits` bits, padded to a multiple of `align` bytes
+    pub(crate) const fn from_bits(bits: u64, align: u64) -> Self {
+        Self {
+            bits,
+            size: bits.div_ceil(8).next_multiple_of(align),
+            align,
+        }
+    }
+
+    /// Check that the layout fits in an object on `target`
+    pub(crate) fn limit(self, target: &crate::TargetSpec) -> Result<Self, LayoutError> {
+        let max_size = target.max_object_size();
+        if self.size > max_size || self.align > max_size {
+            return Err(LayoutError::TooLarge { max_size });
+        }
+
+        Ok(self)
+    }
+
+    /// The alloc size, see [`ConcreteLayout::alloc_size`]
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The number of bits in a value
+    pub const fn bit_size(&self) -> u64 {
+        self.bits
+    }
+
+    /// The number of bytes written by a store, which may be less than the alloc size
+    pub const fn store_size(&self) -> u64 {
+        self.bits.div_ceil(8)
+    }
+
+    /// The offset between consecutive elements of an array, a multiple of the alignment
+    pub const fn alloc_size(&self) -> u64 {
+        self.size
+    }
+
+    pub const fn align(&self) -> u64 {
+        self.align
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TypeKind {
@@ -39,6 +154,29 @@ pub enum TypeKind {
     Pointer,
     Aggregate,
     Func,
+    FnPointer,
+    /// A type parameter of a generic type, see [`ParamTy`](super::ParamTy)
+    Param,
+    /// A named type without a body, see [`OpaqueTy`](super::OpaqueTy)
+    Opaque,
+    /// Another name for a type, see [`AliasTy`](super::AliasTy)
+    Alias,
+}
+
+impl TypeKind {
+    /// Every kind of type, in declaration order
+    pub const ALL: [Self; 10] = [
+        Self::Unit,
+        Self::Int,
+        Self::Float,
+        Self::Pointer,
+        Self::Aggregate,
+        Self::Func,
+        Self::FnPointer,
+        Self::Param,
+        Self::Opaque,
+        Self::Alias,
+    ];
 }
 
 pub trait TypeCallback<'ctx> {
@@ -51,9 +189,9 @@ pub unsafe trait BasicTypeData<'ctx>: 'ctx {
     const KIND: TypeKind;
 
     // align must be a power of 2
-    fn layout(&self, ctx: Context<'ctx>) -> Layout;
+    fn layout(&self, ctx: Context<'ctx>) -> Result<Layout, LayoutError>;
 
-    fn packed_layout(&self, ctx: Context<'ctx>) -> Layout {
+    fn packed_layout(&self, ctx: Context<'ctx>) -> Result<Layout, LayoutError> {
         self.layout(ctx)
     }
 }
@@ -101,9 +239,31 @@ impl<T: ?Sized> PartialEq for RawType<'_, T> {
         self.0 == other.0
     }
 }
+impl<T: ?Sized> core::fmt::Debug for RawType<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Type")
+            .field(&self.header().kind)
+            .field(&self.index())
+            .finish()
+    }
+}
+
+// hash and order by index instead of address, so they are deterministic across runs
 impl<T: ?Sized> Hash for RawType<'_, T> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.hash(state);
+        self.index().hash(state);
+    }
+}
+
+impl<T: ?Sized> PartialOrd for RawType<'_, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: ?Sized> Ord for RawType<'_, T> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.index().cmp(&other.index())
     }
 }
 
@@ -141,6 +301,14 @@ impl<'ctx, T: ?Sized> RawType<'ctx, T> {
         self.0.as_ref()
     }
 
+    /// A dense id for this type, which is its position in the context's creation order
+    ///
+    /// Unlike the address of the type, this is the same on every run which creates the
+    /// same types in the same order.
+    pub fn index(self) -> u32 {
+        self.header().index.get()
+    }
+
     pub fn with_callback<F: TypeCallback<'ctx>>(self, callback: F) -> F::Output {
         let ty = self.erase();
         match self.header().kind {
@@ -150,16 +318,20 @@ impl<'ctx, T: ?Sized> RawType<'ctx, T> {
             TypeKind::Pointer => callback.call(ty.cast::<super::PointerTy>()),
             TypeKind::Aggregate => callback.call(ty.cast::<super::AggregateTy>()),
             TypeKind::Func => callback.call(ty.cast::<super::FuncTy>()),
+            TypeKind::FnPointer => callback.call(ty.cast::<super::FnPointerTy>()),
+            TypeKind::Param => callback.call(ty.cast::<super::ParamTy>()),
+            TypeKind::Opaque => callback.call(ty.cast::<super::OpaqueTy>()),
+            TypeKind::Alias => callback.call(ty.cast::<super::AliasTy>()),
         }
     }
 
-    pub fn layout(&self, ctx: Context<'ctx>) -> Layout {
+    pub fn layout(&self, ctx: Context<'ctx>) -> Result<Layout, LayoutError> {
         struct LayoutCallback<'ctx> {
             ctx: Context<'ctx>,
         }
 
         impl<'ctx> TypeCallback<'ctx> for LayoutCallback<'ctx> {
-            type Output = Layout;
+            type Output = Result<Layout, LayoutError>;
 
             fn call<T: ?Sized + BasicTypeData<'ctx>>(self, x: RawType<'ctx, T>) -> Self::Output {
                 x.get().layout(self.ctx)
@@ -174,6 +346,11 @@ impl TypeHeader {
     pub const fn kind(&self) -> TypeKind {
         self.kind
     }
+
+    pub(crate) fn set_index(&self, index: u32) {
+        debug_assert_eq!(self.index.get(), u32::MAX, "type was registered twice");
+        self.index.set(index);
+    }
 }
 
 impl<'ctx> super::Type<'ctx> {
@@ -221,7 +398,7 @@ impl<'ctx, T: ?Sized> RawType<'ctx, T> {
         L: init::layout_provider::LayoutProvider<T, A>,
     {
         init::try_init_fn(move |ptr| {
-            let ctx_ptr = alloc.try_init::<T, A, L>(args)?;
+            let ctx_ptr = alloc.init_ptr::<T, A, L>(args)?;
             Ok(ptr.write(Self(ctx_ptr)))
         })
     }
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, collections::HashSet, hash::BuildHasherDefault};
+
+use super::{AggregateTy, AliasTy, FnPointerTy, FuncTy, Type, TypeKind, TypeMap};
+
+type FxBuildHasher = BuildHasherDefault<rustc_hash::FxHasher>;
+
+/// Decides whether two types have the same structure, assuming pairs that are already
+/// being compared are equal so recursive types terminate
+struct Bisimulation<'ctx> {
+    assumed: HashSet<(Type<'ctx>, Type<'ctx>), FxBuildHasher>,
+}
+
+impl<'ctx> Bisimulation<'ctx> {
+    fn equal(&mut self, a: Type<'ctx>, b: Type<'ctx>) -> bool {
+        let (a, b) = (a.strip_aliases(), b.strip_aliases());
+        if a == b {
+            return true;
+        }
+
+        if a.kind() != b.kind() || !self.assumed.insert((a, b)) {
+            // either the kinds differ, or we are already comparing this pair further
+            // up, in which case it is equal unless some other part differs
+            return a.kind() == b.kind();
+        }
+
+        match a.kind() {
+            TypeKind::Aggregate => self.aggregates_equal(a.cast(), b.cast()),
+            TypeKind::Func => self.funcs_equal(a.cast(), b.cast()),
+            TypeKind::FnPointer => self.funcs_equal(
+                a.cast::<FnPointerTy>().func(),
+                b.cast::<FnPointerTy>().func(),
+            ),
+            // the remaining kinds are interned by value or are nominal without a body,
+            // so they are equal only if they are the same type
+            TypeKind::Unit
+            | TypeKind::Int
+            | TypeKind::Float
+            | TypeKind::Pointer
+            | TypeKind::Param
+            | TypeKind::Opaque
+            | TypeKind::Alias => false,
+        }
+    }
+
+    fn aggregates_equal(&mut self, a: AggregateTy<'ctx>, b: AggregateTy<'ctx>) -> bool {
+        a.repr() == b.repr()
+            && a.params() == b.params()
+            && a.fields().len() == b.fields().len()
+            && a.fields().iter().zip(b.fields()).all(|(x, y)| {
+                // field names don't contribute to the structure
+                x.bit_width == y.bit_width && x.offset == y.offset && self.equal(x.ty, y.ty)
+            })
+    }
+
+    fn funcs_equal(&mut self, a: FuncTy<'ctx>, b: FuncTy<'ctx>) -> bool {
+        a.args().len() == b.args().len()
+            && self.equal(a.ret(), b.ret())
+            && a.args()
+                .iter()
+                .zip(b.args())
+                .all(|(&x, &y)| self.equal(x, y))
+    }
+}
+
+impl<'ctx> Type<'ctx> {
+    /// Whether `self` and `other` have the same structure, ignoring aliases and the names
+    /// of aggregates and their fields
+    pub fn structurally_equal(self, other: Type<'ctx>) -> bool {
+        Bisimulation {
+            assumed: HashSet::default(),
+        }
+        .equal(self, other)
+    }
+}
+
+impl<'ctx> crate::Context<'ctx> {
+    /// Map every type in the context to a canonical representative of its structure
+    ///
+    /// Each aggregate maps to the first aggregate created that is structurally equal to
+    /// it, and aliases map to the representative of the type they name. Function and
+    /// function pointer types are rebuilt from the representatives of their parts, so
+    /// equivalent signatures map to the same type.
+    pub fn canonicalize(self) -> TypeMap<'ctx, Type<'ctx>> {
+        let mut canonical = TypeMap::new();
+        // candidate representatives, grouped by a cheap summary of their shape
+        let mut representatives = HashMap::<_, Vec<AggregateTy<'ctx>>, FxBuildHasher>::default();
+
+        // types only refer to types created before them, so the parts of a type are
+        // always mapped before the type itself
+        for ty in self.type_ctx().iter_types() {
+            let representative = match ty.kind() {
+                TypeKind::Aggregate => {
+                    let aggregate = ty.cast::<AggregateTy>();
+                    let shape = (
+                        aggregate.params(),
+                        aggregate
+                            .fields()
+                            .iter()
+                            .map(|field| {
+                                let kind = field.ty.strip_aliases().kind();
+                                (kind, field.bit_width, field.offset)
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+
+                    let candidates = representatives.entry(shape).or_default();
+                    match candidates
+                        .iter()
+                        .find(|candidate| candidate.erase().structurally_equal(ty))
+                    {
+                        Some(candidate) => candidate.erase(),
+                        None => {
+                            candidates.push(aggregate);
+                            ty
+                        }
+                    }
+                }
+                TypeKind::Func => {
+                    let func = ty.cast::<FuncTy>();
+                    let ret = canonical.get(func.ret()).copied().unwrap_or(func.ret());
+                    let args = func
+                        .args()
+                        .iter()
+                        .map(|&arg| canonical.get(arg).copied().unwrap_or(arg))
+                        .collect::<Vec<_>>();
+                    self.function(ret, &args)
+                }
+                TypeKind::FnPointer => {
+                    let func = ty.cast::<FnPointerTy>().func().erase();
+                    self.fn_ptr_ty(canonical.get(func).copied().unwrap_or(func))
+                }
+                TypeKind::Alias => {
+                    let aliased = ty.cast::<AliasTy>().aliased();
+                    canonical.get(aliased).copied().unwrap_or(aliased)
+                }
+                TypeKind::Unit
+                | TypeKind::Int
+                | TypeKind::Float
+                | TypeKind::Pointer
+                | TypeKind::Param
+                | TypeKind::Opaque => ty,
+            };
+
+            canonical.insert(ty, representative);
+        }
+
+        canonical
+    }
+}
+
+#[test]
+fn test_structural() {
+    use super::AggregateField;
+
+    crate::Context::with(crate::TEST_TARGET_SPEC, |ctx| {
+        let int = ctx.int_ty(32);
+        let long = ctx.int_ty(64);
+        let field = |name, ty| AggregateField::new(ctx.symbol(name), ty);
+
+        let point = ctx.create_aggregate("Point", [field("x", int), field("y", int)]);
+        let vec2 = ctx.create_aggregate("Vec2", [field("a", int), field("b", int)]);
+        let size = ctx.create_aggregate("Size", [field("w", int), field("h", long)]);
+        let line = ctx.create_aggregate("Line", [field("from", point), field("to", vec2)]);
+        let segment = ctx.create_aggregate("Segment", [field("p", vec2), field("q", vec2)]);
+
+        assert!(point.structurally_equal(vec2));
+        assert!(!point.structurally_equal(size));
+        assert!(line.structurally_equal(segment));
+
+        let takes_point = ctx.function(ctx.unit_ty(), &[point]);
+        let takes_vec2 = ctx.function(ctx.unit_ty(), &[vec2]);
+        assert_ne!(takes_point, takes_vec2);
+        assert!(takes_point.structurally_equal(takes_vec2));
+
+        let canonical = ctx.canonicalize();
+        assert_eq!(canonical.get(vec2), Some(&point));
+        assert_eq!(canonical.get(size), Some(&size));
+        assert_eq!(canonical.get(segment), Some(&line));
+        assert_eq!(canonical.get(takes_vec2), Some(&takes_point));
+
+        // a field behind an alias has the same shape as the type it names
+        let int_t = ctx.create_alias("int_t", int);
+        let aliased = ctx.create_aggregate("Aliased", [field("x", int_t), field("y", int)]);
+        assert!(aliased.structurally_equal(point));
+        assert_eq!(ctx.canonicalize().get(aliased), Some(&point));
+    });
+}